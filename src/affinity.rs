@@ -0,0 +1,181 @@
+//! Sticky replica selection by affinity key.
+
+use crate::context::primary_reads_forced;
+use crate::replica::ReplicaNode;
+use crate::{DbPools, ReadPool};
+
+/// A key that [`DbPools::read_for`] can pin to a replica.
+///
+/// Keys are hashed from these bytes, so two processes agree on where a key
+/// goes as long as they produce the same bytes for it. Integers use their
+/// little-endian encoding (`usize` and `isize` as 64-bit), strings their
+/// UTF-8 bytes. For other keys, such as UUIDs, pass their byte
+/// representation.
+pub trait AffinityKey {
+    /// The bytes the key is hashed from.
+    fn key_bytes(&self) -> impl AsRef<[u8]>;
+}
+
+macro_rules! affinity_key_int {
+    ($($int:ty),*) => {
+        $(
+            impl AffinityKey for $int {
+                fn key_bytes(&self) -> impl AsRef<[u8]> {
+                    self.to_le_bytes()
+                }
+            }
+        )*
+    };
+}
+
+affinity_key_int!(u8, u16, u32, u64, u128, i8, i16, i32, i64, i128);
+
+impl AffinityKey for usize {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        (*self as u64).to_le_bytes()
+    }
+}
+
+impl AffinityKey for isize {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        (*self as i64).to_le_bytes()
+    }
+}
+
+impl AffinityKey for str {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        self.as_bytes()
+    }
+}
+
+impl AffinityKey for String {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        self.as_bytes()
+    }
+}
+
+impl AffinityKey for [u8] {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        self
+    }
+}
+
+impl<const N: usize> AffinityKey for [u8; N] {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        self
+    }
+}
+
+impl AffinityKey for Vec<u8> {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        self.as_slice()
+    }
+}
+
+impl<T: AffinityKey + ?Sized> AffinityKey for &T {
+    fn key_bytes(&self) -> impl AsRef<[u8]> {
+        (**self).key_bytes()
+    }
+}
+
+impl DbPools {
+    /// Get a read pool that stays the same for a given affinity key.
+    ///
+    /// Reads for the same key, such as a user or tenant ID, go to the same
+    /// replica, which keeps its caches warm and keeps successive reads
+    /// monotonic. Keys are spread over the in-rotation replicas in proportion
+    /// to their weights with rendezvous hashing: when a replica is added,
+    /// removed or taken out of rotation, only the keys it gains or loses
+    /// move. Replicas are identified by their [name](crate::Replica::name).
+    ///
+    /// The configured [`RoutingPolicy`](crate::RoutingPolicy) isn't
+    /// consulted. If the replica's circuit breaker turns the read away, the
+    /// next-ranked replica is used. If no replica is in rotation, or inside
+    /// a [`with_primary_reads`](crate::with_primary_reads) scope, the
+    /// primary is returned.
+    ///
+    /// The mapping hashes the bytes of the replica name and of the key (see
+    /// [`AffinityKey`]) with 64-bit FNV-1a, so it only depends on the names,
+    /// the weights and the key: processes on different platforms or built
+    /// with different Rust versions agree on it.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example(pools: DbPools, tenant_id: i64) -> Result<(), sqlx::Error> {
+    /// let projects: Vec<(i64, String)> =
+    ///     sqlx::query_as("SELECT id, name FROM projects WHERE tenant_id = $1")
+    ///         .bind(tenant_id)
+    ///         .fetch_all(pools.read_for(&tenant_id))
+    ///         .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn read_for(&self, key: &(impl AffinityKey + ?Sized)) -> &ReadPool {
+        if primary_reads_forced() {
+            return ReadPool::new(self.primary.current());
+        }
+        let key = key.key_bytes();
+        let ranked = self
+            .replicas
+            .iter()
+            .filter(|replica| replica.effective_weight() > 0)
+            .map(|replica| (score(replica, key.as_ref()), replica));
+        let Some((_, best)) = ranked.clone().max_by(|(a, _), (b, _)| a.total_cmp(b)) else {
+            return ReadPool::new(self.primary.current());
+        };
+        if self.admits(best) {
+            return ReadPool::new(&best.pool);
+        }
+        // Only sort the rest when the best replica's breaker turns us away
+        let mut rest: Vec<_> = ranked
+            .filter(|(_, replica)| !std::ptr::eq(*replica, best))
+            .collect();
+        rest.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+        match rest.into_iter().find(|(_, replica)| self.admits(replica)) {
+            Some((_, replica)) => ReadPool::new(&replica.pool),
+            None => ReadPool::new(self.primary.current()),
+        }
+    }
+}
+
+/// Weighted rendezvous score of `replica` for `key`; the highest wins.
+///
+/// `weight / -ln(h)` for a hash `h` uniform in (0, 1) gives each replica a
+/// share of keys proportional to its weight.
+fn score(replica: &ReplicaNode, key: &[u8]) -> f64 {
+    let hash = mix(fnv1a(replica.name_hash, key));
+    // Map to (0, 1), never 0 or 1 so the logarithm stays finite and non-zero
+    let unit = ((hash >> 11) as f64 + 0.5) / (1u64 << 53) as f64;
+    f64::from(replica.effective_weight()) / -unit.ln()
+}
+
+/// MurmurHash3's 64-bit finalizer, spreading FNV-1a's output over the high
+/// bits the score uses.
+fn mix(mut hash: u64) -> u64 {
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xff51_afd7_ed55_8ccd);
+    hash ^= hash >> 33;
+    hash = hash.wrapping_mul(0xc4ce_b9fe_1a85_ec53);
+    hash ^ (hash >> 33)
+}
+
+/// Starting state of 64-bit FNV-1a.
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// Continue a 64-bit FNV-1a hash from `state` over `bytes`.
+fn fnv1a(state: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(state, |state, &byte| {
+        (state ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    })
+}
+
+/// FNV-1a state after a replica's name, which each key's bytes continue.
+///
+/// The name is followed by `0xff`, which never occurs in UTF-8, so a name
+/// and a key can't run into each other.
+pub(crate) fn name_hash(name: &str) -> u64 {
+    fnv1a(fnv1a(FNV_OFFSET_BASIS, name.as_bytes()), &[0xff])
+}
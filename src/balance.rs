@@ -0,0 +1,258 @@
+//! Load balancing strategies for spreading reads across replicas.
+
+use crate::routing::{Candidate, RoutingPolicy};
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Strategy used by [`DbPools`](crate::DbPools) to pick a replica for each read.
+///
+/// Each variant selects one of the built-in [`RoutingPolicy`]
+/// implementations; use [`DbPools::routing_policy`](crate::DbPools::routing_policy)
+/// for your own. Every strategy honors replica weights (see [`Replica::weight`](crate::Replica::weight)).
+/// Replicas with a weight of `0` are never selected. With the default weight
+/// of `1` on every replica, reads are spread evenly.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx::PgPool;
+/// use sqlx_pool_router::{DbPools, LoadBalance};
+///
+/// # async fn example() -> Result<(), sqlx::Error> {
+/// let primary = PgPool::connect("postgresql://primary/db").await?;
+/// let replica_a = PgPool::connect("postgresql://replica-a/db").await?;
+/// let replica_b = PgPool::connect("postgresql://replica-b/db").await?;
+///
+/// let pools = DbPools::with_replicas(primary, vec![replica_a, replica_b])
+///     .load_balance(LoadBalance::LeastBusy);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum LoadBalance {
+    /// Cycle through the replicas in order.
+    ///
+    /// A replica with weight `n` is visited `n` times per cycle.
+    #[default]
+    RoundRobin,
+    /// Pick a replica at random, with probability proportional to its weight.
+    Random,
+    /// Pick the replica with the fewest connections in use relative to its weight.
+    ///
+    /// Connections in use are computed from the pool's `size()` minus
+    /// `num_idle()`. Ties are broken in round-robin order so that idle
+    /// replicas still share the load.
+    LeastBusy,
+    /// Prefer the replica that has been answering fastest.
+    ///
+    /// Each replica tracks an exponentially weighted moving average of how
+    /// long its reads take, connection acquire included. Two replicas are
+    /// drawn at random in proportion to their weights, and the one with the
+    /// lower average, scaled by its connections in use and divided by its
+    /// weight, is used ("power of two choices"). The fastest replica gets
+    /// most reads without every read piling onto it.
+    ///
+    /// Latencies come from reads whose outcome `DbPools` sees: health check
+    /// probes, the closure helpers such as
+    /// [`read_with_fallback`](crate::DbPools::read_with_fallback), and
+    /// [`record_read`](crate::DbPools::record_read). Replicas without any
+    /// measurement yet are preferred, so they get measured.
+    LeastLatency,
+}
+
+impl LoadBalance {
+    /// The built-in [`RoutingPolicy`] implementing this strategy.
+    pub(crate) fn policy(self) -> Arc<dyn RoutingPolicy> {
+        match self {
+            LoadBalance::RoundRobin => Arc::new(RoundRobin::new()),
+            LoadBalance::Random => Arc::new(Random::new()),
+            LoadBalance::LeastBusy => Arc::new(LeastBusy::new()),
+            LoadBalance::LeastLatency => Arc::new(LeastLatency::new()),
+        }
+    }
+}
+
+/// [`LoadBalance::RoundRobin`] as a [`RoutingPolicy`].
+#[derive(Debug, Default)]
+pub struct RoundRobin {
+    cursor: AtomicUsize,
+}
+
+impl RoundRobin {
+    /// Start the rotation at the first replica.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RoutingPolicy for RoundRobin {
+    fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+        let total = total_weight(candidates, |_| true)?;
+        let ticket = self.cursor.fetch_add(1, Ordering::Relaxed) as u64 % total;
+        pick_by_ticket(candidates, |_| true, ticket)
+    }
+}
+
+/// [`LoadBalance::Random`] as a [`RoutingPolicy`].
+#[derive(Debug, Default)]
+pub struct Random {
+    _private: (),
+}
+
+impl Random {
+    /// Create the policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RoutingPolicy for Random {
+    fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+        let total = total_weight(candidates, |_| true)?;
+        pick_by_ticket(candidates, |_| true, fastrand::u64(..total))
+    }
+}
+
+/// [`LoadBalance::LeastBusy`] as a [`RoutingPolicy`].
+#[derive(Debug, Default)]
+pub struct LeastBusy {
+    cursor: AtomicUsize,
+}
+
+impl LeastBusy {
+    /// Start breaking ties at the first replica.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RoutingPolicy for LeastBusy {
+    fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+        let len = candidates.len();
+        let start = self.cursor.fetch_add(1, Ordering::Relaxed);
+        (0..len)
+            .map(|offset| ((start + offset) % len, &candidates[(start + offset) % len]))
+            .map(|(index, candidate)| (index, candidate.weight(), candidate.in_use()))
+            .filter(|&(_, weight, _)| weight > 0)
+            // Compare in_use / weight without dividing
+            .min_by(|(_, weight_a, in_use_a), (_, weight_b, in_use_b)| {
+                let load_a = *in_use_a as u64 * u64::from(*weight_b);
+                let load_b = *in_use_b as u64 * u64::from(*weight_a);
+                load_a.cmp(&load_b)
+            })
+            .map(|(index, _, _)| index)
+    }
+}
+
+/// [`LoadBalance::LeastLatency`] as a [`RoutingPolicy`].
+#[derive(Debug, Default)]
+pub struct LeastLatency {
+    _private: (),
+}
+
+impl LeastLatency {
+    /// Create the policy.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl RoutingPolicy for LeastLatency {
+    fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+        let total = total_weight(candidates, |_| true)?;
+        let first = pick_by_ticket(candidates, |_| true, fastrand::u64(..total))?;
+        let rest = total.saturating_sub(u64::from(candidates[first].weight()));
+        if rest == 0 {
+            return Some(first);
+        }
+        let second = pick_by_ticket(candidates, |index| index != first, fastrand::u64(..rest))
+            .unwrap_or(first);
+        if latency_cost(&candidates[second]) < latency_cost(&candidates[first]) {
+            Some(second)
+        } else {
+            Some(first)
+        }
+    }
+}
+
+/// Sum of the routing weights of the candidates `include` accepts, or
+/// `None` if none of them can be picked.
+pub(crate) fn total_weight(
+    candidates: &[Candidate<'_>],
+    include: impl Fn(usize) -> bool,
+) -> Option<u64> {
+    let total = (0..candidates.len())
+        .filter(|&index| include(index))
+        .map(|index| u64::from(candidates[index].weight()))
+        .sum();
+    (total > 0).then_some(total)
+}
+
+/// Expected cost of sending a read to `candidate`: its average latency,
+/// scaled by the connections in use and divided by its weight.
+fn latency_cost(candidate: &Candidate<'_>) -> u128 {
+    let load = candidate.in_use() as u128 + 1;
+    let weight = u128::from(candidate.weight().max(1));
+    u128::from(candidate.node().latency.nanos()) * load / weight
+}
+
+/// Walk the cumulative weights of the candidates `include` accepts to find
+/// the one owning `ticket`.
+pub(crate) fn pick_by_ticket(
+    candidates: &[Candidate<'_>],
+    include: impl Fn(usize) -> bool,
+    mut ticket: u64,
+) -> Option<usize> {
+    let mut selectable = (0..candidates.len()).filter(|&index| include(index));
+    for index in selectable.clone() {
+        let weight = u64::from(candidates[index].weight());
+        if ticket < weight {
+            return Some(index);
+        }
+        ticket -= weight;
+    }
+    // Weights changed concurrently; fall back to any selectable replica
+    selectable.find(|&index| candidates[index].weight() > 0)
+}
+
+/// Number of connections currently checked out of a pool.
+pub(crate) fn in_use(pool: &PgPool) -> usize {
+    (pool.size() as usize).saturating_sub(pool.num_idle())
+}
+
+/// Exponentially weighted moving average of a replica's read latency.
+///
+/// Each new sample counts for a fifth of the average. Zero means no
+/// sample has been recorded yet.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyEwma {
+    nanos: AtomicU64,
+}
+
+impl LatencyEwma {
+    pub(crate) fn record(&self, latency: Duration) {
+        let sample = latency.as_nanos().clamp(1, u128::from(u64::MAX / 4)) as u64;
+        let _ = self
+            .nanos
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+                Some(if average == 0 {
+                    sample
+                } else {
+                    (average * 4 + sample) / 5
+                })
+            });
+    }
+
+    pub(crate) fn nanos(&self) -> u64 {
+        self.nanos.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn get(&self) -> Option<Duration> {
+        match self.nanos() {
+            0 => None,
+            nanos => Some(Duration::from_nanos(nanos)),
+        }
+    }
+}
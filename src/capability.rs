@@ -0,0 +1,212 @@
+//! Pool types that encode read or write access in function signatures.
+
+use futures_core::future::BoxFuture;
+use futures_core::stream::BoxStream;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgQueryResult, PgRow, PgStatement, PgTypeInfo};
+use sqlx::{Acquire, Describe, Either, Execute, Executor, PgPool, Postgres, Transaction};
+use std::ops::Deref;
+
+/// A pool for read operations, returned by [`PoolProvider::read`](crate::PoolProvider::read).
+///
+/// Queries run through it like through a `&PgPool`, but it has none of the
+/// pool's other methods: no [`begin`](WritePool::begin), no
+/// [`acquire`](WritePool::acquire), and it doesn't implement
+/// [`sqlx::Acquire`]. Repository functions that take `&ReadPool` say in
+/// their signature that they only read, and can be handed either pool
+/// (a [`WritePool`] dereferences to a `ReadPool`). Functions that take
+/// `&WritePool` can't be given a read pool by mistake.
+///
+/// The type states intent; it doesn't parse SQL. An `INSERT` run through a
+/// `ReadPool` is still sent to the server, where a replica (or a
+/// [`TestDbPools`](crate::TestDbPools) read pool) rejects it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx_pool_router::{DbPools, PoolProvider, ReadPool, WritePool};
+///
+/// async fn find_user(db: &ReadPool, id: i64) -> Result<String, sqlx::Error> {
+///     sqlx::query_scalar("SELECT name FROM users WHERE id = $1")
+///         .bind(id)
+///         .fetch_one(db)
+///         .await
+/// }
+///
+/// async fn rename_user(db: &WritePool, id: i64, name: &str) -> Result<(), sqlx::Error> {
+///     sqlx::query("UPDATE users SET name = $1 WHERE id = $2")
+///         .bind(name)
+///         .bind(id)
+///         .execute(db)
+///         .await?;
+///     Ok(())
+/// }
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// rename_user(pools.write(), 1, "Alice").await?;
+/// // Read back from the primary; a write pool can always read
+/// let name = find_user(pools.write(), 1).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Passing a read pool where a write is required doesn't compile:
+///
+/// ```rust,compile_fail
+/// # use sqlx_pool_router::{DbPools, PoolProvider, WritePool};
+/// # async fn rename_user(db: &WritePool, id: i64, name: &str) {}
+/// # async fn example(pools: DbPools) {
+/// rename_user(pools.read(), 1, "Alice").await;
+/// # }
+/// ```
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct ReadPool(PgPool);
+
+impl ReadPool {
+    /// View a `PgPool` as a read pool.
+    ///
+    /// For implementing [`PoolProvider`](crate::PoolProvider) on your own types.
+    pub fn new(pool: &PgPool) -> &ReadPool {
+        // SAFETY: `ReadPool` is `#[repr(transparent)]` over its only field, a
+        // `PgPool`, so both types have the same size, alignment and layout
+        // and a valid `&PgPool` is a valid `&ReadPool`. The returned reference
+        // borrows `pool` with the same lifetime, and `ReadPool` adds no
+        // invariants of its own that the cast could bypass.
+        unsafe { &*(pool as *const PgPool as *const ReadPool) }
+    }
+
+    pub(crate) fn pool(&self) -> &PgPool {
+        &self.0
+    }
+}
+
+/// A pool for write operations, returned by [`PoolProvider::write`](crate::PoolProvider::write).
+///
+/// Has everything needed to change data: queries,
+/// [`begin`](Self::begin), [`acquire`](Self::acquire) and
+/// [`sqlx::Acquire`] (so it works with `sqlx::migrate!`). It dereferences to
+/// a [`ReadPool`], so it can be passed to functions that only read.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx_pool_router::{DbPools, PoolProvider};
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let mut tx = pools.write().begin().await?;
+/// sqlx::query("INSERT INTO users (name) VALUES ('Alice')")
+///     .execute(&mut *tx)
+///     .await?;
+/// tx.commit().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+#[repr(transparent)]
+pub struct WritePool(PgPool);
+
+impl WritePool {
+    /// View a `PgPool` as a write pool.
+    ///
+    /// For implementing [`PoolProvider`](crate::PoolProvider) on your own types.
+    pub fn new(pool: &PgPool) -> &WritePool {
+        // SAFETY: `WritePool` is `#[repr(transparent)]` over its only field, a
+        // `PgPool`, so both types have the same size, alignment and layout
+        // and a valid `&PgPool` is a valid `&WritePool`. The returned reference
+        // borrows `pool` with the same lifetime, and `WritePool` adds no
+        // invariants of its own that the cast could bypass.
+        unsafe { &*(pool as *const PgPool as *const WritePool) }
+    }
+
+    /// Start a transaction. See [`PgPool::begin`].
+    pub async fn begin(&self) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+        self.0.begin().await
+    }
+
+    /// Take a connection out of the pool. See [`PgPool::acquire`].
+    pub async fn acquire(&self) -> Result<PoolConnection<Postgres>, sqlx::Error> {
+        self.0.acquire().await
+    }
+
+    pub(crate) fn pool(&self) -> &PgPool {
+        &self.0
+    }
+}
+
+impl Deref for WritePool {
+    type Target = ReadPool;
+
+    fn deref(&self) -> &ReadPool {
+        ReadPool::new(&self.0)
+    }
+}
+
+impl<'a> Acquire<'a> for &WritePool {
+    type Database = Postgres;
+    type Connection = PoolConnection<Postgres>;
+
+    fn acquire(self) -> BoxFuture<'a, Result<PoolConnection<Postgres>, sqlx::Error>> {
+        Acquire::acquire(&self.0)
+    }
+
+    fn begin(self) -> BoxFuture<'a, Result<Transaction<'a, Postgres>, sqlx::Error>> {
+        Acquire::begin(&self.0)
+    }
+}
+
+/// Implement `Executor` for references to a pool newtype by delegating to the
+/// wrapped `PgPool`.
+macro_rules! pool_executor {
+    ($name:ident) => {
+        impl<'p> Executor<'p> for &'_ $name {
+            type Database = Postgres;
+
+            fn fetch_many<'e, 'q: 'e, E>(
+                self,
+                query: E,
+            ) -> BoxStream<'e, Result<Either<PgQueryResult, PgRow>, sqlx::Error>>
+            where
+                'p: 'e,
+                E: 'q + Execute<'q, Postgres>,
+            {
+                self.0.fetch_many(query)
+            }
+
+            fn fetch_optional<'e, 'q: 'e, E>(
+                self,
+                query: E,
+            ) -> BoxFuture<'e, Result<Option<PgRow>, sqlx::Error>>
+            where
+                'p: 'e,
+                E: 'q + Execute<'q, Postgres>,
+            {
+                self.0.fetch_optional(query)
+            }
+
+            fn prepare_with<'e, 'q: 'e>(
+                self,
+                sql: &'q str,
+                parameters: &'e [PgTypeInfo],
+            ) -> BoxFuture<'e, Result<PgStatement<'q>, sqlx::Error>>
+            where
+                'p: 'e,
+            {
+                self.0.prepare_with(sql, parameters)
+            }
+
+            fn describe<'e, 'q: 'e>(
+                self,
+                sql: &'q str,
+            ) -> BoxFuture<'e, Result<Describe<Postgres>, sqlx::Error>>
+            where
+                'p: 'e,
+            {
+                self.0.describe(sql)
+            }
+        }
+    };
+}
+
+pool_executor!(ReadPool);
+pool_executor!(WritePool);
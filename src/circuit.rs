@@ -0,0 +1,383 @@
+//! Per-replica circuit breakers driven by the outcome of reads.
+
+use crate::replica::ReplicaNode;
+use crate::retry::is_connection_error;
+use crate::{DbPools, ReadPool};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Settings for the replica circuit breakers enabled with
+/// [`DbPools::circuit_breaker`].
+///
+/// Each replica keeps the outcome of its last [`window`](Self::window)
+/// reads. Once at least [`min_reads`](Self::min_reads) have been recorded
+/// and the share of failures reaches [`failure_rate`](Self::failure_rate),
+/// the circuit opens and the replica is taken out of rotation for the
+/// [`cooldown`](Self::cooldown). A read fails when its error matches
+/// [`when`](Self::when) (by default, one that [`is_connection_error`]
+/// recognizes) or when it takes longer than [`slow_read`](Self::slow_read).
+///
+/// After the cooldown the circuit is half-open: up to
+/// [`trial_reads`](Self::trial_reads) reads are routed to the replica, even
+/// when many are issued at once, and the rest go to the primary. If they
+/// all succeed the circuit closes; any failure opens it again.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use sqlx_pool_router::CircuitBreakerConfig;
+///
+/// let config = CircuitBreakerConfig::new()
+///     .failure_rate(0.25)
+///     .slow_read(Duration::from_millis(500))
+///     .cooldown(Duration::from_secs(10));
+/// ```
+#[derive(Clone, Debug)]
+pub struct CircuitBreakerConfig {
+    failure_rate: f64,
+    slow_read: Option<Duration>,
+    window: usize,
+    min_reads: usize,
+    cooldown: Duration,
+    trial_reads: u32,
+    when: fn(&sqlx::Error) -> bool,
+}
+
+impl CircuitBreakerConfig {
+    /// Create a config with the defaults: open when half of the last 20
+    /// reads failed, with at least 10 recorded, for 30 seconds, then close
+    /// after 3 successful trial reads. No latency threshold.
+    pub fn new() -> Self {
+        Self {
+            failure_rate: 0.5,
+            slow_read: None,
+            window: 20,
+            min_reads: 10,
+            cooldown: Duration::from_secs(30),
+            trial_reads: 3,
+            when: is_connection_error,
+        }
+    }
+
+    /// Share of failed reads, between `0.0` and `1.0`, that opens the circuit.
+    pub fn failure_rate(mut self, rate: f64) -> Self {
+        self.failure_rate = rate.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Count reads slower than `latency` as failures, even if they succeed.
+    pub fn slow_read(mut self, latency: Duration) -> Self {
+        self.slow_read = Some(latency);
+        self
+    }
+
+    /// Number of recent reads the failure rate is computed over.
+    ///
+    /// Values below `1` are treated as `1`. Lowers
+    /// [`min_reads`](Self::min_reads) to the window size if it's larger.
+    pub fn window(mut self, reads: usize) -> Self {
+        self.window = reads.max(1);
+        self.min_reads = self.min_reads.min(self.window);
+        self
+    }
+
+    /// Reads that must be recorded before the circuit can open.
+    ///
+    /// Only the last [`window`](Self::window) reads are kept, so values
+    /// above the window size are treated as the window size.
+    pub fn min_reads(mut self, reads: usize) -> Self {
+        self.min_reads = reads.min(self.window);
+        self
+    }
+
+    /// How long an open circuit keeps the replica out of rotation.
+    pub fn cooldown(mut self, cooldown: Duration) -> Self {
+        self.cooldown = cooldown;
+        self
+    }
+
+    /// Successful reads needed to close a half-open circuit.
+    ///
+    /// Values below `1` are treated as `1`.
+    pub fn trial_reads(mut self, reads: u32) -> Self {
+        self.trial_reads = reads.max(1);
+        self
+    }
+
+    /// Choose which errors count as failures.
+    pub fn when(mut self, predicate: fn(&sqlx::Error) -> bool) -> Self {
+        self.when = predicate;
+        self
+    }
+}
+
+impl Default for CircuitBreakerConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// State of a replica's circuit breaker, as returned by
+/// [`DbPools::replica_circuits`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Reads are routed normally.
+    Closed,
+    /// The replica is out of rotation until the cooldown ends.
+    Open,
+    /// A limited number of trial reads are routed to the replica.
+    HalfOpen,
+}
+
+/// Circuit breaker embedded in each replica's routing state.
+#[derive(Debug, Default)]
+pub(crate) struct Breaker {
+    /// Whether the circuit has left the closed state, so routing a read,
+    /// which checks every replica, only takes the lock when it has.
+    tripped: AtomicBool,
+    inner: Mutex<BreakerInner>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerInner {
+    circuit: Circuit,
+    /// Recent outcomes while closed, `true` for a failure.
+    outcomes: VecDeque<bool>,
+}
+
+#[derive(Debug, Default)]
+enum Circuit {
+    #[default]
+    Closed,
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        /// Trial reads that may still be routed to the replica.
+        permits: u32,
+        successes: u32,
+        /// When the permits run out without results coming back, they're
+        /// handed out again after this instant.
+        refresh: Instant,
+    },
+}
+
+impl Breaker {
+    /// Whether the replica may be selected for a read.
+    ///
+    /// Only a hint for routing: a half-open circuit's permits are handed
+    /// out by [`admit`](Self::admit).
+    pub(crate) fn is_available(&self) -> bool {
+        if !self.tripped.load(Ordering::Acquire) {
+            return true;
+        }
+        match self.inner.lock().unwrap().circuit {
+            Circuit::Closed => true,
+            Circuit::Open { until } => Instant::now() >= until,
+            Circuit::HalfOpen {
+                permits, refresh, ..
+            } => permits > 0 || Instant::now() >= refresh,
+        }
+    }
+
+    /// Claim the right to route a read to the replica, using up a trial
+    /// permit when the circuit isn't closed. Returns `false` when no permit
+    /// is left, in which case the read must go elsewhere.
+    pub(crate) fn admit(&self, config: &CircuitBreakerConfig) -> bool {
+        if !self.tripped.load(Ordering::Acquire) {
+            return true;
+        }
+        let inner = &mut *self.inner.lock().unwrap();
+        let now = Instant::now();
+        match &mut inner.circuit {
+            Circuit::Closed => true,
+            Circuit::Open { until } => {
+                if now < *until {
+                    return false;
+                }
+                inner.circuit = Circuit::HalfOpen {
+                    permits: config.trial_reads - 1,
+                    successes: 0,
+                    refresh: now + config.cooldown,
+                };
+                true
+            }
+            Circuit::HalfOpen {
+                permits,
+                successes,
+                refresh,
+            } => {
+                if *permits == 0 && now >= *refresh {
+                    *permits = config.trial_reads.saturating_sub(*successes);
+                    *refresh = now + config.cooldown;
+                }
+                if *permits == 0 {
+                    return false;
+                }
+                *permits -= 1;
+                true
+            }
+        }
+    }
+
+    pub(crate) fn record(
+        &self,
+        config: &CircuitBreakerConfig,
+        latency: Duration,
+        error: Option<&sqlx::Error>,
+    ) {
+        let failed =
+            error.is_some_and(config.when) || config.slow_read.is_some_and(|limit| latency > limit);
+        let inner = &mut *self.inner.lock().unwrap();
+        let open = Circuit::Open {
+            until: Instant::now() + config.cooldown,
+        };
+        match &mut inner.circuit {
+            Circuit::Closed => {
+                if inner.outcomes.len() >= config.window {
+                    inner.outcomes.pop_front();
+                }
+                inner.outcomes.push_back(failed);
+                let reads = inner.outcomes.len();
+                let failures = inner.outcomes.iter().filter(|&&failed| failed).count();
+                if failures > 0
+                    && reads >= config.min_reads
+                    && failures as f64 >= config.failure_rate * reads as f64
+                {
+                    inner.outcomes.clear();
+                    inner.circuit = open;
+                    self.tripped.store(true, Ordering::Release);
+                }
+            }
+            // Reads that started before the circuit opened
+            Circuit::Open { .. } => {}
+            Circuit::HalfOpen { successes, .. } => {
+                if failed {
+                    inner.circuit = open;
+                } else {
+                    *successes += 1;
+                    if *successes >= config.trial_reads {
+                        inner.circuit = Circuit::Closed;
+                        self.tripped.store(false, Ordering::Release);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn state(&self) -> CircuitState {
+        if !self.tripped.load(Ordering::Acquire) {
+            return CircuitState::Closed;
+        }
+        match self.inner.lock().unwrap().circuit {
+            Circuit::Closed => CircuitState::Closed,
+            Circuit::Open { until } if Instant::now() < until => CircuitState::Open,
+            Circuit::Open { .. } | Circuit::HalfOpen { .. } => CircuitState::HalfOpen,
+        }
+    }
+}
+
+impl DbPools {
+    /// Enable a circuit breaker on every replica.
+    ///
+    /// Replicas whose recent reads fail or run slow too often are taken out
+    /// of rotation, like unhealthy replicas, and let back in gradually after
+    /// a cooldown. See [`CircuitBreakerConfig`] for the thresholds. If every
+    /// circuit is open, reads go to the primary.
+    ///
+    /// Breakers only see reads whose outcome they're told about: the ones
+    /// run through [`retry_read`](Self::retry_read),
+    /// [`read_with_fallback`](Self::read_with_fallback) and
+    /// [`hedged_read`](Self::hedged_read), and the ones passed to
+    /// [`record_read`](Self::record_read).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::{CircuitBreakerConfig, DbPools};
+    ///
+    /// # fn example(primary: PgPool, replicas: Vec<PgPool>) {
+    /// let pools = DbPools::with_replicas(primary, replicas).circuit_breaker(
+    ///     CircuitBreakerConfig::new().slow_read(Duration::from_secs(1)),
+    /// );
+    /// # }
+    /// ```
+    pub fn circuit_breaker(mut self, config: CircuitBreakerConfig) -> Self {
+        self.breaker = Some(std::sync::Arc::new(config));
+        self
+    }
+
+    /// Report the outcome of a read run on `pool`.
+    ///
+    /// Feeds the replica's circuit breaker, when
+    /// [`circuit_breaker`](Self::circuit_breaker) is enabled, and the
+    /// latency average used by [`LoadBalance::LeastLatency`](crate::LoadBalance::LeastLatency).
+    /// Reads that failed to reach the server don't count towards the
+    /// latency. For reads run directly on
+    /// [`read()`](crate::PoolProvider::read) rather than through one of the
+    /// closure helpers. Does nothing when `pool` isn't a replica.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::time::Instant;
+    /// use sqlx_pool_router::{DbPools, PoolProvider};
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// let pool = pools.read();
+    /// let started = Instant::now();
+    /// let result = sqlx::query("SELECT 1").execute(pool).await;
+    /// pools.record_read(pool, started.elapsed(), result.as_ref().err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn record_read(&self, pool: &ReadPool, latency: Duration, error: Option<&sqlx::Error>) {
+        let Some(index) = self.replica_index(pool) else {
+            return;
+        };
+        let replica = &self.replicas[index];
+        if !error.is_some_and(is_connection_error) {
+            replica.latency.record(latency);
+        }
+        if let Some(config) = &self.breaker {
+            replica.breaker.record(config, latency, error);
+        }
+    }
+
+    /// Current circuit breaker state of every replica, in the order they
+    /// were provided. Always [`Closed`](CircuitState::Closed) when
+    /// [`circuit_breaker`](Self::circuit_breaker) isn't enabled.
+    pub fn replica_circuits(&self) -> Vec<CircuitState> {
+        self.replicas
+            .iter()
+            .map(|replica| replica.breaker.state())
+            .collect()
+    }
+
+    /// Whether a read may be routed to `replica`, claiming a trial permit if
+    /// its circuit is half-open. Always `true` without a circuit breaker.
+    pub(crate) fn admits(&self, replica: &ReplicaNode) -> bool {
+        self.breaker
+            .as_ref()
+            .is_none_or(|config| replica.breaker.admit(config))
+    }
+
+    /// Run `read` on `pool` and report its outcome with
+    /// [`record_read`](Self::record_read).
+    pub(crate) async fn observe<T>(
+        &self,
+        pool: &ReadPool,
+        read: impl Future<Output = Result<T, sqlx::Error>>,
+    ) -> Result<T, sqlx::Error> {
+        let started = Instant::now();
+        let result = read.await;
+        self.record_read(pool, started.elapsed(), result.as_ref().err());
+        result
+    }
+}
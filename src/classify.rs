@@ -0,0 +1,244 @@
+//! Classifying SQL statements as reads or writes.
+
+#[cfg(feature = "sql-parser")]
+pub(crate) mod parser;
+
+/// Whether a statement can run on a replica.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum StatementKind {
+    /// Only reads data; safe to run on a replica.
+    Read,
+    /// Modifies data or state, takes row locks, or couldn't be proven
+    /// read-only; must run on the primary.
+    Write,
+}
+
+/// Statements that start with one of these can be reads.
+const READ_LEADERS: &[&str] = &["SELECT", "WITH", "SHOW", "VALUES", "TABLE", "EXPLAIN"];
+
+/// Keywords that make any statement a write wherever they appear: DML
+/// (including inside writable CTEs) and `SELECT ... INTO`.
+const WRITE_KEYWORDS: &[&str] = &["INSERT", "UPDATE", "DELETE", "MERGE", "TRUNCATE", "INTO"];
+
+/// Built-in functions that change state, need a transaction ID, or take or
+/// release locks that only mean something on the primary.
+const WRITE_FUNCTIONS: &[&str] = &[
+    "nextval",
+    "setval",
+    "currval",
+    "lastval",
+    "txid_current",
+    "pg_current_xact_id",
+    "pg_notify",
+    "pg_advisory_lock",
+    "pg_advisory_lock_shared",
+    "pg_advisory_xact_lock",
+    "pg_advisory_xact_lock_shared",
+    "pg_try_advisory_lock",
+    "pg_try_advisory_lock_shared",
+    "pg_try_advisory_xact_lock",
+    "pg_try_advisory_xact_lock_shared",
+    "pg_advisory_unlock",
+    "pg_advisory_unlock_shared",
+    "pg_advisory_unlock_all",
+    "lo_create",
+    "lo_creat",
+    "lo_import",
+    "lo_from_bytea",
+    "lo_put",
+    "lo_unlink",
+    "dblink_exec",
+];
+
+fn is_write_function(name: &str) -> bool {
+    WRITE_FUNCTIONS
+        .iter()
+        .any(|function| function.eq_ignore_ascii_case(name))
+}
+
+/// Words that may follow `FOR` in a row-locking clause
+/// (`FOR UPDATE`, `FOR NO KEY UPDATE`, `FOR SHARE`, `FOR KEY SHARE`).
+const LOCK_WORDS: &[&str] = &["UPDATE", "NO", "SHARE", "KEY"];
+
+/// Classify a SQL string as a read or a write.
+///
+/// Plain `SELECT`, `WITH ... SELECT`, `SHOW`, `VALUES`, `TABLE` and `EXPLAIN`
+/// statements are [`Read`](StatementKind::Read). Everything else is a
+/// [`Write`](StatementKind::Write), as is any statement containing DML
+/// (including writable CTEs), a row-locking clause such as `FOR UPDATE`,
+/// `SELECT ... INTO`, or a call to a built-in function with side effects
+/// such as `nextval`, `pg_notify` or `pg_advisory_lock`.
+///
+/// By default the SQL is scanned for keywords, ignoring string literals,
+/// quoted identifiers and comments. The scan errs on the side of
+/// [`Write`](StatementKind::Write): a column that happens to be named
+/// `update` sends the query to the primary, which is always correct, just
+/// not load-balanced.
+///
+/// With the `sql-parser` feature, the SQL is parsed with a PostgreSQL
+/// grammar and the syntax tree is inspected instead, so keywords used as
+/// identifiers don't matter and function calls are recognized wherever they
+/// appear. Statements the parser doesn't understand fall back to the scan.
+/// That includes a writable CTE running a `DELETE`, such as
+/// `WITH x AS (DELETE ... RETURNING *) SELECT ...`, which sqlparser 0.53
+/// can't parse; the scan still classifies it as a write, but keywords used
+/// as identifiers elsewhere in such a statement count again.
+///
+/// User-defined functions that write can't be detected either way; send
+/// queries that call them to [`write()`](crate::PoolProvider::write)
+/// explicitly.
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::{classify, StatementKind};
+///
+/// assert_eq!(classify("SELECT * FROM users"), StatementKind::Read);
+/// assert_eq!(classify("SELECT * FROM users FOR UPDATE"), StatementKind::Write);
+/// assert_eq!(
+///     classify("WITH gone AS (DELETE FROM users RETURNING id) SELECT count(*) FROM gone"),
+///     StatementKind::Write
+/// );
+/// ```
+pub fn classify(sql: &str) -> StatementKind {
+    #[cfg(feature = "sql-parser")]
+    if let Some(kind) = parser::classify(sql) {
+        return kind;
+    }
+    scan(sql)
+}
+
+/// Classify by keyword scanning.
+fn scan(sql: &str) -> StatementKind {
+    let words = words(sql);
+    let Some(first) = words.first() else {
+        return StatementKind::Write;
+    };
+    if !READ_LEADERS.contains(&first.as_str()) {
+        return StatementKind::Write;
+    }
+
+    let writes = words.iter().enumerate().any(|(i, word)| {
+        WRITE_KEYWORDS.contains(&word.as_str())
+            || is_write_function(word)
+            || (word == "FOR"
+                && words
+                    .get(i + 1)
+                    .is_some_and(|next| LOCK_WORDS.contains(&next.as_str())))
+    });
+    if writes {
+        StatementKind::Write
+    } else {
+        StatementKind::Read
+    }
+}
+
+/// Uppercased bare words in `sql`, skipping literals, quoted identifiers,
+/// parameters and comments.
+fn words(sql: &str) -> Vec<String> {
+    let bytes = sql.as_bytes();
+    let mut words = Vec::new();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                i = find(bytes, i + 2, b"\n").map_or(bytes.len(), |end| end + 1);
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => i = skip_block_comment(bytes, i),
+            b'\'' => i = skip_quoted(bytes, i, b'\'', false),
+            // E'...' strings allow backslash escapes
+            b'E' | b'e' if bytes.get(i + 1) == Some(&b'\'') => {
+                i = skip_quoted(bytes, i + 1, b'\'', true);
+            }
+            b'"' => i = skip_quoted(bytes, i, b'"', false),
+            b'$' => i = skip_dollar(bytes, i),
+            b if b.is_ascii_alphabetic() || b == b'_' || b >= 0x80 => {
+                let start = i;
+                while i < bytes.len()
+                    && (bytes[i].is_ascii_alphanumeric()
+                        || matches!(bytes[i], b'_' | b'$')
+                        || bytes[i] >= 0x80)
+                {
+                    i += 1;
+                }
+                words.push(sql[start..i].to_ascii_uppercase());
+            }
+            _ => i += 1,
+        }
+    }
+    words
+}
+
+fn find(bytes: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    bytes
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|offset| from + offset)
+}
+
+/// Skip a (possibly nested) `/* ... */` comment starting at `start`.
+fn skip_block_comment(bytes: &[u8], start: usize) -> usize {
+    let mut depth = 0;
+    let mut i = start;
+    while i + 1 < bytes.len() {
+        match (bytes[i], bytes[i + 1]) {
+            (b'/', b'*') => {
+                depth += 1;
+                i += 2;
+            }
+            (b'*', b'/') => {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            _ => i += 1,
+        }
+    }
+    bytes.len()
+}
+
+/// Skip a literal delimited by `quote` starting at `start`. A doubled quote
+/// is an escaped quote; with `escapes`, so is a backslash-escaped one.
+fn skip_quoted(bytes: &[u8], start: usize, quote: u8, escapes: bool) -> usize {
+    let mut i = start + 1;
+    while i < bytes.len() {
+        if escapes && bytes[i] == b'\\' {
+            i += 2;
+        } else if bytes[i] == quote {
+            if bytes.get(i + 1) == Some(&quote) {
+                i += 2;
+            } else {
+                return i + 1;
+            }
+        } else {
+            i += 1;
+        }
+    }
+    bytes.len()
+}
+
+/// Skip a `$n` parameter or a `$tag$ ... $tag$` string starting at `start`.
+fn skip_dollar(bytes: &[u8], start: usize) -> usize {
+    let mut i = start + 1;
+    if bytes.get(i).is_some_and(u8::is_ascii_digit) {
+        while bytes.get(i).is_some_and(u8::is_ascii_digit) {
+            i += 1;
+        }
+        return i;
+    }
+    while bytes
+        .get(i)
+        .is_some_and(|b| b.is_ascii_alphanumeric() || *b == b'_')
+    {
+        i += 1;
+    }
+    if bytes.get(i) != Some(&b'$') {
+        return i;
+    }
+    let tag = &bytes[start..=i];
+    find(bytes, i + 1, tag).map_or(bytes.len(), |end| end + tag.len())
+}
@@ -0,0 +1,75 @@
+//! Classification from a parsed syntax tree (`sql-parser` feature).
+
+use super::{is_write_function, StatementKind};
+use sqlparser::ast::{Expr, ObjectName, Query, SetExpr, Statement, TableFactor, Visit, Visitor};
+use sqlparser::dialect::PostgreSqlDialect;
+use sqlparser::parser::Parser;
+use std::ops::ControlFlow;
+
+/// Classify `sql` from its syntax tree, or `None` if it doesn't parse.
+pub(crate) fn classify(sql: &str) -> Option<StatementKind> {
+    let statements = Parser::parse_sql(&PostgreSqlDialect {}, sql).ok()?;
+    if statements.is_empty() {
+        return None;
+    }
+    if statements.visit(&mut FindWrite).is_break() {
+        Some(StatementKind::Write)
+    } else {
+        Some(StatementKind::Read)
+    }
+}
+
+/// Stops at the first node that can't run on a replica.
+struct FindWrite;
+
+impl Visitor for FindWrite {
+    type Break = ();
+
+    // Called for nested statements too, such as DML in a writable CTE or
+    // the statement under EXPLAIN.
+    fn pre_visit_statement(&mut self, statement: &Statement) -> ControlFlow<()> {
+        match statement {
+            Statement::Query(_)
+            | Statement::Explain { .. }
+            | Statement::ShowVariable { .. }
+            | Statement::ShowVariables { .. } => ControlFlow::Continue(()),
+            _ => ControlFlow::Break(()),
+        }
+    }
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<()> {
+        let select_into = matches!(&*query.body, SetExpr::Select(select) if select.into.is_some());
+        if select_into || !query.locks.is_empty() {
+            ControlFlow::Break(())
+        } else {
+            ControlFlow::Continue(())
+        }
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<()> {
+        match expr {
+            Expr::Function(function) => check_function(&function.name),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+
+    fn pre_visit_table_factor(&mut self, table_factor: &TableFactor) -> ControlFlow<()> {
+        match table_factor {
+            TableFactor::Function { name, .. }
+            | TableFactor::Table {
+                name,
+                args: Some(_),
+                ..
+            } => check_function(name),
+            _ => ControlFlow::Continue(()),
+        }
+    }
+}
+
+fn check_function(name: &ObjectName) -> ControlFlow<()> {
+    // Ignore the schema, so `pg_catalog.nextval` counts as `nextval`
+    match name.0.last() {
+        Some(ident) if is_write_function(&ident.value) => ControlFlow::Break(()),
+        _ => ControlFlow::Continue(()),
+    }
+}
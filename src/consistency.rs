@@ -0,0 +1,329 @@
+//! Read-your-writes consistency using WAL positions.
+//!
+//! [`Lsn`] is the raw WAL position. [`ConsistencyToken`] wraps it with a
+//! stable, versioned encoding for passing between services.
+
+use crate::context::primary_reads_forced;
+use crate::{DbPools, ReadPool};
+use sqlx::PgPool;
+use std::fmt;
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::task::JoinSet;
+use tokio::time::Instant;
+
+/// How often replicas are re-checked while waiting for them to catch up.
+const REPLAY_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// The server's own WAL position: the replay position on a standby, the
+/// current write position otherwise.
+const REPLAY_LSN_QUERY: &str = "\
+    SELECT (CASE WHEN pg_is_in_recovery() THEN pg_last_wal_replay_lsn() \
+        ELSE pg_current_wal_lsn() END)::text";
+
+/// A PostgreSQL write-ahead log position (`pg_lsn`).
+///
+/// Formats and parses using PostgreSQL's `XXXXXXXX/XXXXXXXX` notation.
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::Lsn;
+///
+/// let lsn: Lsn = "16/B374D848".parse().unwrap();
+/// assert_eq!(lsn.to_string(), "16/B374D848");
+/// assert!(lsn > "16/B374D000".parse().unwrap());
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Lsn(u64);
+
+impl Lsn {
+    /// Build an LSN from its 64-bit value.
+    pub const fn from_u64(value: u64) -> Self {
+        Self(value)
+    }
+
+    /// The 64-bit value of this LSN.
+    pub const fn as_u64(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 & 0xFFFF_FFFF)
+    }
+}
+
+/// Error returned when parsing an [`Lsn`] from a string fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseLsnError(String);
+
+impl fmt::Display for ParseLsnError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid LSN {:?}: expected XXXXXXXX/XXXXXXXX", self.0)
+    }
+}
+
+impl std::error::Error for ParseLsnError {}
+
+impl FromStr for Lsn {
+    type Err = ParseLsnError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseLsnError(s.to_string());
+        let (high, low) = s.split_once('/').ok_or_else(invalid)?;
+        let high = u32::from_str_radix(high, 16).map_err(|_| invalid())?;
+        let low = u32::from_str_radix(low, 16).map_err(|_| invalid())?;
+        Ok(Self((u64::from(high) << 32) | u64::from(low)))
+    }
+}
+
+/// Marker that a read must be at least as fresh as some earlier write.
+///
+/// Tokens are built on the primary's WAL position and can be carried across
+/// service boundaries: service A captures a token after writing, passes it
+/// along in an HTTP header or message, and service B hands it to
+/// [`DbPools::read_consistent`] to read what A wrote. Tokens are only
+/// meaningful for the database cluster that issued them.
+///
+/// # Encoding
+///
+/// The string form is `v1.` followed by the LSN as 16 uppercase hex digits,
+/// e.g. `v1.00000016B374D848`. It contains only URL- and header-safe
+/// characters and will stay readable by future versions of this crate.
+/// With the `serde` feature, tokens serialize as this string.
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::{ConsistencyToken, Lsn};
+///
+/// let token = ConsistencyToken::from("16/B374D848".parse::<Lsn>().unwrap());
+/// let encoded = token.to_string();
+/// assert_eq!(encoded, "v1.00000016B374D848");
+///
+/// let decoded: ConsistencyToken = encoded.parse().unwrap();
+/// assert_eq!(decoded, token);
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ConsistencyToken(Lsn);
+
+impl ConsistencyToken {
+    const PREFIX: &'static str = "v1.";
+
+    /// The WAL position this token requires reads to have reached.
+    pub const fn lsn(self) -> Lsn {
+        self.0
+    }
+
+    /// Combine two tokens into one that is at least as fresh as both.
+    ///
+    /// Useful when a request depends on writes from several upstream services.
+    pub fn merge(self, other: Self) -> Self {
+        self.max(other)
+    }
+}
+
+impl From<Lsn> for ConsistencyToken {
+    fn from(lsn: Lsn) -> Self {
+        Self(lsn)
+    }
+}
+
+impl fmt::Display for ConsistencyToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{:016X}", Self::PREFIX, self.0.as_u64())
+    }
+}
+
+/// Error returned when decoding a [`ConsistencyToken`] from a string fails.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParseTokenError(String);
+
+impl fmt::Display for ParseTokenError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid consistency token {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseTokenError {}
+
+impl FromStr for ConsistencyToken {
+    type Err = ParseTokenError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || ParseTokenError(s.to_string());
+        let hex = s.strip_prefix(Self::PREFIX).ok_or_else(invalid)?;
+        if hex.len() != 16 || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return Err(invalid());
+        }
+        let value = u64::from_str_radix(hex, 16).map_err(|_| invalid())?;
+        Ok(Self(Lsn::from_u64(value)))
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for ConsistencyToken {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for ConsistencyToken {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let s = <std::borrow::Cow<'de, str>>::deserialize(deserializer)?;
+        s.parse().map_err(serde::de::Error::custom)
+    }
+}
+
+/// Fetch the WAL position a server has reached, or `None` if it can't be read.
+async fn replayed_lsn(pool: &PgPool) -> Option<Lsn> {
+    let lsn: Option<String> = sqlx::query_scalar(REPLAY_LSN_QUERY)
+        .fetch_one(pool)
+        .await
+        .ok()?;
+    lsn?.parse().ok()
+}
+
+impl DbPools {
+    /// Capture the primary's current WAL position.
+    ///
+    /// Call this after a write commits and pass the result to
+    /// [`read_after_lsn`](Self::read_after_lsn) to make later reads observe
+    /// that write.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use sqlx_pool_router::{DbPools, PoolProvider};
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// sqlx::query("UPDATE users SET name = 'Alice' WHERE id = 1")
+    ///     .execute(pools.write())
+    ///     .await?;
+    /// let lsn = pools.current_wal_lsn().await?;
+    ///
+    /// // Only a replica that has replayed the update (or the primary) is used
+    /// let pool = pools.read_after_lsn(lsn, Duration::from_millis(100)).await;
+    /// let name: String = sqlx::query_scalar("SELECT name FROM users WHERE id = 1")
+    ///     .fetch_one(pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn current_wal_lsn(&self) -> Result<Lsn, sqlx::Error> {
+        let lsn: String = sqlx::query_scalar("SELECT pg_current_wal_lsn()::text")
+            .fetch_one(self.primary.current())
+            .await?;
+        lsn.parse().map_err(|e| sqlx::Error::Decode(Box::new(e)))
+    }
+
+    /// Capture a [`ConsistencyToken`] for everything written to the primary so far.
+    ///
+    /// Equivalent to [`current_wal_lsn`](Self::current_wal_lsn), wrapped for
+    /// passing to other services.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::{DbPools, PoolProvider};
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// sqlx::query("INSERT INTO orders (id) VALUES (1)")
+    ///     .execute(pools.write())
+    ///     .await?;
+    ///
+    /// // e.g. send as an `X-Consistency-Token` header
+    /// let header = pools.consistency_token().await?.to_string();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn consistency_token(&self) -> Result<ConsistencyToken, sqlx::Error> {
+        self.current_wal_lsn().await.map(ConsistencyToken::from)
+    }
+
+    /// Get a pool for reads that must observe the writes behind `token`.
+    ///
+    /// Routes exactly like [`read_after_lsn`](Self::read_after_lsn).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use sqlx_pool_router::{ConsistencyToken, DbPools};
+    ///
+    /// # async fn example(pools: DbPools, header: &str) -> Result<(), Box<dyn std::error::Error>> {
+    /// let token: ConsistencyToken = header.parse()?;
+    /// let pool = pools.read_consistent(token, Duration::from_millis(100)).await;
+    /// let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+    ///     .fetch_one(pool)
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_consistent(&self, token: ConsistencyToken, max_wait: Duration) -> &ReadPool {
+        self.read_after_lsn(token.lsn(), max_wait).await
+    }
+
+    /// Get a pool for reads that must observe everything up to `lsn`.
+    ///
+    /// Returns a replica whose `pg_last_wal_replay_lsn()` has reached `lsn`,
+    /// chosen from the ones that have by the configured
+    /// [`RoutingPolicy`](crate::RoutingPolicy). Replicas out of rotation (unhealthy, lagging or
+    /// with a weight of `0`) are not considered.
+    ///
+    /// Replicas are checked concurrently and, if none has caught up,
+    /// re-checked until `max_wait` elapses. Checks still running at that
+    /// point are abandoned, so a hung replica can't hold the read up, and
+    /// the primary is returned. With a `max_wait` of zero the primary is
+    /// returned straight away. Inside a [`with_primary_reads`](crate::with_primary_reads)
+    /// scope the primary is returned straight away.
+    pub async fn read_after_lsn(&self, lsn: Lsn, max_wait: Duration) -> &ReadPool {
+        if primary_reads_forced() {
+            return ReadPool::new(self.primary.current());
+        }
+        let deadline = Instant::now() + max_wait;
+        while Instant::now() < deadline {
+            if let Some(pool) = self.replica_at_lsn(lsn, deadline).await {
+                return ReadPool::new(pool);
+            }
+            tokio::time::sleep_until((Instant::now() + REPLAY_POLL_INTERVAL).min(deadline)).await;
+        }
+        ReadPool::new(self.primary.current())
+    }
+
+    /// Find an in-rotation replica that has replayed up to `lsn`.
+    ///
+    /// Every in-rotation replica is checked concurrently, giving up on the
+    /// ones that haven't answered by `deadline`. The routing policy only
+    /// chooses among the ones that have caught up, so polling doesn't
+    /// advance its state.
+    async fn replica_at_lsn(&self, lsn: Lsn, deadline: Instant) -> Option<&PgPool> {
+        let mut probes = JoinSet::new();
+        for (index, replica) in self.replicas.iter().enumerate() {
+            if replica.effective_weight() == 0 {
+                continue;
+            }
+            let pool = replica.pool.clone();
+            probes.spawn(async move {
+                let replayed = tokio::time::timeout_at(deadline, replayed_lsn(&pool)).await;
+                (index, replayed.ok().flatten() >= Some(lsn))
+            });
+        }
+        let mut caught_up = Vec::new();
+        while let Some(probe) = probes.join_next().await {
+            if let Ok((index, true)) = probe {
+                caught_up.push(index);
+            }
+        }
+        caught_up.sort_unstable();
+        let first = *caught_up.first()?;
+        let replica = self
+            .select_among(caught_up)
+            .unwrap_or(&self.replicas[first]);
+        Some(&replica.pool)
+    }
+}
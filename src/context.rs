@@ -0,0 +1,51 @@
+//! Task-local routing overrides.
+
+use std::future::Future;
+
+tokio::task_local! {
+    static FORCE_PRIMARY_READS: ();
+}
+
+/// Run `future` with every read routed to the primary.
+///
+/// Inside the scope, [`read()`](crate::PoolProvider::read) on [`DbPools`](crate::DbPools)
+/// (and on [`Session`](crate::Session) and [`TestDbPools`](crate::TestDbPools))
+/// returns the primary pool, without having to thread a flag through every
+/// function that takes `impl PoolProvider`. Useful for request handlers that
+/// must see their own writes.
+///
+/// The override is task-local: it covers everything awaited inside
+/// `future`, but not tasks started with `tokio::spawn` from within it.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx_pool_router::{with_primary_reads, DbPools, PoolProvider};
+///
+/// async fn load_profile<P: PoolProvider>(pools: &P, id: i64) -> Result<String, sqlx::Error> {
+///     sqlx::query_scalar("SELECT name FROM users WHERE id = $1")
+///         .bind(id)
+///         .fetch_one(pools.read())
+///         .await
+/// }
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let name = with_primary_reads(async {
+///     sqlx::query("UPDATE users SET name = 'Alice' WHERE id = 1")
+///         .execute(pools.write())
+///         .await?;
+///     // Reads from the primary, so it sees the update
+///     load_profile(&pools, 1).await
+/// })
+/// .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub async fn with_primary_reads<F: Future>(future: F) -> F::Output {
+    FORCE_PRIMARY_READS.scope((), future).await
+}
+
+/// Whether the current task is inside a [`with_primary_reads`] scope.
+pub fn primary_reads_forced() -> bool {
+    FORCE_PRIMARY_READS.try_with(|_| ()).is_ok()
+}
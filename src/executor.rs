@@ -0,0 +1,245 @@
+//! `sqlx::Executor` implementations that route queries between pools.
+
+use crate::{classify, PoolProvider, StatementKind};
+use futures_core::future::BoxFuture;
+use futures_core::stream::BoxStream;
+use sqlx::pool::PoolConnection;
+use sqlx::postgres::{PgQueryResult, PgRow, PgStatement, PgTypeInfo};
+use sqlx::{Acquire, Describe, Either, Execute, Executor, PgPool, Postgres, Transaction};
+use std::fmt;
+
+/// An executor that sends each query to the read or write pool based on its SQL.
+///
+/// Obtained from [`PoolProvider::auto`]. Statements that [`classify`] as
+/// [`Read`](StatementKind::Read) go to [`read()`](PoolProvider::read); everything
+/// else goes to [`write()`](PoolProvider::write). Routing goes through the
+/// provider, so load balancing, sticky sessions and
+/// [`with_primary_reads`](crate::with_primary_reads) all still apply.
+///
+/// Each query is routed on its own: don't rely on two queries run through
+/// `auto()` seeing the same connection or the same data. Use a transaction
+/// on [`write()`](PoolProvider::write) for that.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx_pool_router::{DbPools, PoolProvider};
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// // Goes to a replica
+/// let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+///     .fetch_one(pools.auto())
+///     .await?;
+///
+/// // Goes to the primary
+/// sqlx::query("UPDATE users SET active = false WHERE id = $1")
+///     .bind(1_i64)
+///     .execute(pools.auto())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct AutoRouter<'p, P> {
+    pools: &'p P,
+}
+
+impl<'p, P: PoolProvider> AutoRouter<'p, P> {
+    /// Route queries through `pools`.
+    pub fn new(pools: &'p P) -> Self {
+        Self { pools }
+    }
+
+    /// The pool a statement with this SQL would be sent to.
+    pub fn route(&self, sql: &str) -> &'p PgPool {
+        match classify(sql) {
+            StatementKind::Read => self.pools.read().pool(),
+            StatementKind::Write => self.pools.write().pool(),
+        }
+    }
+}
+
+impl<'p, P: PoolProvider> Route<'p> for AutoRouter<'p, P> {
+    fn pool_for(&self, sql: &str) -> &'p PgPool {
+        self.route(sql)
+    }
+}
+
+/// An executor that always uses the read pool.
+///
+/// Obtained from [`PoolProvider::read_executor`]. The pool is resolved from
+/// [`read()`](PoolProvider::read) each time a query runs or a connection is
+/// acquired, so the handle can be stored and passed around like a pool while
+/// load balancing, health checks and
+/// [`with_primary_reads`](crate::with_primary_reads) keep applying. Two
+/// queries may therefore run on different replicas; [`acquire`](Acquire::acquire)
+/// a connection to keep them together.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx::{Acquire, Postgres};
+/// use sqlx_pool_router::{DbPools, PoolProvider};
+///
+/// async fn count_users<'c>(db: impl Acquire<'c, Database = Postgres>) -> Result<i64, sqlx::Error> {
+///     let mut conn = db.acquire().await?;
+///     sqlx::query_scalar("SELECT COUNT(*) FROM users")
+///         .fetch_one(&mut *conn)
+///         .await
+/// }
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let count = count_users(pools.read_executor()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ReadExecutor<'p, P> {
+    pools: &'p P,
+}
+
+impl<'p, P: PoolProvider> ReadExecutor<'p, P> {
+    /// Run queries on the read pool of `pools`.
+    pub fn new(pools: &'p P) -> Self {
+        Self { pools }
+    }
+}
+
+impl<'p, P: PoolProvider> Route<'p> for ReadExecutor<'p, P> {
+    fn pool_for(&self, _sql: &str) -> &'p PgPool {
+        self.pools.read().pool()
+    }
+}
+
+/// An executor that always uses the write pool.
+///
+/// Obtained from [`PoolProvider::write_executor`]. The pool is resolved from
+/// [`write()`](PoolProvider::write) each time a query runs or a connection
+/// is acquired, so the handle keeps following the primary after a failover.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx::Acquire;
+/// use sqlx_pool_router::{DbPools, PoolProvider};
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let mut tx = pools.write_executor().begin().await?;
+/// sqlx::query("INSERT INTO users (name) VALUES ('Alice')")
+///     .execute(&mut *tx)
+///     .await?;
+/// tx.commit().await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct WriteExecutor<'p, P> {
+    pools: &'p P,
+}
+
+impl<'p, P: PoolProvider> WriteExecutor<'p, P> {
+    /// Run queries on the write pool of `pools`.
+    pub fn new(pools: &'p P) -> Self {
+        Self { pools }
+    }
+}
+
+impl<'p, P: PoolProvider> Route<'p> for WriteExecutor<'p, P> {
+    fn pool_for(&self, _sql: &str) -> &'p PgPool {
+        self.pools.write().pool()
+    }
+}
+
+/// Picks the pool a query runs on.
+trait Route<'p> {
+    fn pool_for(&self, sql: &str) -> &'p PgPool;
+}
+
+/// Implement `Clone`, `Copy`, `Debug` and `Executor` for a routing handle by
+/// delegating each query to the pool its [`Route`] impl picks.
+macro_rules! routing_executor {
+    ($name:ident) => {
+        impl<P> Clone for $name<'_, P> {
+            fn clone(&self) -> Self {
+                *self
+            }
+        }
+
+        impl<P> Copy for $name<'_, P> {}
+
+        impl<P> fmt::Debug for $name<'_, P> {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.debug_struct(stringify!($name)).finish_non_exhaustive()
+            }
+        }
+
+        impl<'p, P: PoolProvider> Executor<'p> for $name<'p, P> {
+            type Database = Postgres;
+
+            fn fetch_many<'e, 'q: 'e, E>(
+                self,
+                query: E,
+            ) -> BoxStream<'e, Result<Either<PgQueryResult, PgRow>, sqlx::Error>>
+            where
+                'p: 'e,
+                E: 'q + Execute<'q, Postgres>,
+            {
+                self.pool_for(query.sql()).fetch_many(query)
+            }
+
+            fn fetch_optional<'e, 'q: 'e, E>(
+                self,
+                query: E,
+            ) -> BoxFuture<'e, Result<Option<PgRow>, sqlx::Error>>
+            where
+                'p: 'e,
+                E: 'q + Execute<'q, Postgres>,
+            {
+                self.pool_for(query.sql()).fetch_optional(query)
+            }
+
+            fn prepare_with<'e, 'q: 'e>(
+                self,
+                sql: &'q str,
+                parameters: &'e [PgTypeInfo],
+            ) -> BoxFuture<'e, Result<PgStatement<'q>, sqlx::Error>>
+            where
+                'p: 'e,
+            {
+                self.pool_for(sql).prepare_with(sql, parameters)
+            }
+
+            fn describe<'e, 'q: 'e>(
+                self,
+                sql: &'q str,
+            ) -> BoxFuture<'e, Result<Describe<Postgres>, sqlx::Error>>
+            where
+                'p: 'e,
+            {
+                self.pool_for(sql).describe(sql)
+            }
+        }
+    };
+}
+
+/// Implement `Acquire` for a handle that takes connections from the pool
+/// returned by the provider's `$pool` method.
+macro_rules! routing_acquire {
+    ($name:ident, $pool:ident) => {
+        impl<'p, P: PoolProvider> Acquire<'p> for $name<'p, P> {
+            type Database = Postgres;
+            type Connection = PoolConnection<Postgres>;
+
+            fn acquire(self) -> BoxFuture<'p, Result<PoolConnection<Postgres>, sqlx::Error>> {
+                Acquire::acquire(self.pools.$pool().pool())
+            }
+
+            fn begin(self) -> BoxFuture<'p, Result<Transaction<'p, Postgres>, sqlx::Error>> {
+                Acquire::begin(self.pools.$pool().pool())
+            }
+        }
+    };
+}
+
+routing_executor!(AutoRouter);
+routing_executor!(ReadExecutor);
+routing_executor!(WriteExecutor);
+routing_acquire!(ReadExecutor, read);
+routing_acquire!(WriteExecutor, write);
@@ -0,0 +1,258 @@
+//! Primary discovery and failover handling.
+
+use crate::{DbPools, Replica, WritePool};
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+
+/// True when the server accepts writes: not a standby and not read-only by default.
+const WRITABLE_QUERY: &str =
+    "SELECT NOT pg_is_in_recovery() AND current_setting('transaction_read_only') = 'off'";
+
+/// The pools that may be the primary, and which one currently is.
+///
+/// Pools built with a single primary have exactly one candidate and never switch.
+#[derive(Debug)]
+pub(crate) struct PrimarySet {
+    candidates: Box<[PgPool]>,
+    current: AtomicUsize,
+    /// Completed refresh rounds, so callers that waited for one can tell.
+    rounds: AtomicU64,
+    /// Whether the last round switched primary, or `None` if it failed.
+    refreshing: tokio::sync::Mutex<Option<bool>>,
+}
+
+impl PrimarySet {
+    pub(crate) fn new(candidates: Vec<PgPool>, current: usize) -> Self {
+        Self {
+            candidates: candidates.into(),
+            current: AtomicUsize::new(current),
+            rounds: AtomicU64::new(0),
+            refreshing: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    pub(crate) fn current(&self) -> &PgPool {
+        &self.candidates[self.index()]
+    }
+
+    pub(crate) fn index(&self) -> usize {
+        self.current.load(Ordering::Acquire)
+    }
+
+    pub(crate) fn candidates(&self) -> &[PgPool] {
+        &self.candidates
+    }
+
+    /// Re-check the current primary and switch to another candidate if it
+    /// no longer accepts writes. Returns `true` if the primary changed.
+    async fn refresh(&self) -> Result<bool, sqlx::Error> {
+        // Let one caller re-probe while the rest wait for its answer
+        let seen = self.rounds.load(Ordering::Acquire);
+        let mut last = self.refreshing.lock().await;
+        if self.rounds.load(Ordering::Acquire) != seen {
+            // A failed round's error can't be shared, so probe again then
+            if let Some(changed) = *last {
+                return Ok(changed);
+            }
+        }
+        let result = self.probe().await;
+        *last = result.as_ref().ok().copied();
+        self.rounds.fetch_add(1, Ordering::Release);
+        result
+    }
+
+    async fn probe(&self) -> Result<bool, sqlx::Error> {
+        let current = self.index();
+        if let Ok(true) = is_writable(&self.candidates[current]).await {
+            return Ok(false);
+        }
+        let others = (0..self.candidates.len()).filter(|&i| i != current);
+        let index = find_writable(&self.candidates, others).await?;
+        self.current.store(index, Ordering::Release);
+        Ok(true)
+    }
+}
+
+async fn is_writable(pool: &PgPool) -> Result<bool, sqlx::Error> {
+    sqlx::query_scalar(WRITABLE_QUERY).fetch_one(pool).await
+}
+
+/// Probe `indices` in order and return the first candidate that accepts writes.
+async fn find_writable(
+    candidates: &[PgPool],
+    indices: impl IntoIterator<Item = usize>,
+) -> Result<usize, sqlx::Error> {
+    let mut last_error = None;
+    for index in indices {
+        match is_writable(&candidates[index]).await {
+            Ok(true) => return Ok(index),
+            Ok(false) => {}
+            Err(e) => last_error = Some(e),
+        }
+    }
+    Err(last_error.unwrap_or_else(|| {
+        sqlx::Error::Configuration(
+            format!(
+                "no writable primary among {} candidate pool(s)",
+                candidates.len()
+            )
+            .into(),
+        )
+    }))
+}
+
+/// Whether an error suggests the primary has moved or gone away.
+///
+/// Matches writes rejected by a read-only server (SQLSTATE `25006`),
+/// connection failures (class `08`), server shutdowns (`57P01`-`57P03`), and
+/// client-side I/O, TLS, protocol and pool timeout errors.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx_pool_router::{is_failover_error, DbPools, PoolProvider};
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let result = sqlx::query("INSERT INTO events DEFAULT VALUES")
+///     .execute(pools.write())
+///     .await;
+/// if let Err(e) = &result {
+///     if is_failover_error(e) {
+///         pools.refresh_primary().await?;
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn is_failover_error(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db) => db.code().is_some_and(|code| {
+            code == "25006"
+                || code.starts_with("08")
+                || matches!(&*code, "57P01" | "57P02" | "57P03")
+        }),
+        sqlx::Error::Io(_)
+        | sqlx::Error::Tls(_)
+        | sqlx::Error::Protocol(_)
+        | sqlx::Error::PoolTimedOut => true,
+        _ => false,
+    }
+}
+
+impl DbPools {
+    /// Create a DbPools that finds the writable primary among several candidates.
+    ///
+    /// Each candidate is asked whether it accepts writes (it is not in
+    /// recovery, per `pg_is_in_recovery()`, and not read-only by default).
+    /// The first writable candidate becomes the primary. After a failover,
+    /// call [`refresh_primary`](Self::refresh_primary), or use
+    /// [`write_with_failover`](Self::write_with_failover), to re-point
+    /// [`write()`](crate::PoolProvider::write) at the new primary without
+    /// restarting.
+    ///
+    /// Fails with the last connection error if no candidate could be reached,
+    /// or with [`sqlx::Error::Configuration`] if every reachable candidate is
+    /// read-only.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example() -> Result<(), sqlx::Error> {
+    /// let candidates = vec![
+    ///     PgPool::connect_lazy("postgresql://db-1/app")?,
+    ///     PgPool::connect_lazy("postgresql://db-2/app")?,
+    ///     PgPool::connect_lazy("postgresql://db-3/app")?,
+    /// ];
+    /// let replicas = vec![PgPool::connect_lazy("postgresql://db-replicas/app")?];
+    ///
+    /// let pools = DbPools::discover(candidates, replicas).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn discover<R>(
+        candidates: Vec<PgPool>,
+        replicas: impl IntoIterator<Item = R>,
+    ) -> Result<Self, sqlx::Error>
+    where
+        R: Into<Replica>,
+    {
+        let index = find_writable(&candidates, 0..candidates.len()).await?;
+        Ok(Self::from_parts(
+            PrimarySet::new(candidates, index),
+            replicas,
+        ))
+    }
+
+    /// Re-check that the primary still accepts writes, switching to another
+    /// candidate if it doesn't.
+    ///
+    /// Returns `Ok(true)` if the primary changed. Concurrent calls are
+    /// coalesced so a burst of errors triggers a single round of probes:
+    /// calls made while a round is running wait for it and return its
+    /// result, unless it failed, in which case they probe again. Pools with
+    /// a single primary simply re-check it.
+    pub async fn refresh_primary(&self) -> Result<bool, sqlx::Error> {
+        self.primary.refresh().await
+    }
+
+    /// The pools that may serve as primary, in the order they were provided.
+    pub fn primary_candidates(&self) -> impl ExactSizeIterator<Item = &PgPool> {
+        self.primary.candidates().iter()
+    }
+
+    /// Position of the current primary among [`primary_candidates`](Self::primary_candidates).
+    pub fn primary_index(&self) -> usize {
+        self.primary.index()
+    }
+
+    /// Run a write against the primary, following a failover if one happens.
+    ///
+    /// If `write` fails with an error that [`is_failover_error`] recognizes,
+    /// the primary is re-discovered. When a different candidate turns out to
+    /// be the primary now, `write` is retried once against it. Otherwise the
+    /// original error is returned.
+    ///
+    /// `write` must be idempotent. A connection can drop after the old
+    /// primary committed the write but before the result arrived, so the
+    /// retry may apply it a second time. Make it safe to repeat, for example
+    /// with `INSERT ... ON CONFLICT DO NOTHING` or a client-generated key.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example(pools: DbPools, user_id: i64) -> Result<(), sqlx::Error> {
+    /// // Keyed by a client-generated ID, so running it twice inserts one row
+    /// pools
+    ///     .write_with_failover(async |pool| {
+    ///         sqlx::query("INSERT INTO users (id, name) VALUES ($1, 'Alice') ON CONFLICT (id) DO NOTHING")
+    ///             .bind(user_id)
+    ///             .execute(pool)
+    ///             .await
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn write_with_failover<T, F>(&self, mut write: F) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&WritePool) -> Result<T, sqlx::Error>,
+    {
+        let before = self.primary.index();
+        let error = match write(WritePool::new(self.primary.current())).await {
+            Ok(value) => return Ok(value),
+            Err(e) if is_failover_error(&e) => e,
+            Err(e) => return Err(e),
+        };
+        match self.primary.refresh().await {
+            Ok(_) if self.primary.index() != before => {
+                write(WritePool::new(self.primary.current())).await
+            }
+            _ => Err(error),
+        }
+    }
+}
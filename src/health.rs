@@ -0,0 +1,262 @@
+//! Background health checking for replica pools.
+
+use crate::replica::{spawn_monitor, ReplicaNode};
+use crate::DbPools;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Settings for replica health checks.
+///
+/// Each check runs `SELECT 1` against every replica. A replica is taken out
+/// of rotation after [`unhealthy_threshold`](Self::unhealthy_threshold)
+/// consecutive failures and put back after
+/// [`healthy_threshold`](Self::healthy_threshold) consecutive successes.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use sqlx_pool_router::HealthCheckConfig;
+///
+/// let config = HealthCheckConfig::new()
+///     .interval(Duration::from_secs(2))
+///     .timeout(Duration::from_secs(1))
+///     .unhealthy_threshold(3);
+/// ```
+#[derive(Clone, Debug)]
+pub struct HealthCheckConfig {
+    interval: Duration,
+    timeout: Duration,
+    unhealthy_threshold: u32,
+    healthy_threshold: u32,
+}
+
+impl HealthCheckConfig {
+    /// Create a config with the defaults: check every 5 seconds, time out
+    /// after 2 seconds, and require 2 consecutive results to change state.
+    pub fn new() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            timeout: Duration::from_secs(2),
+            unhealthy_threshold: 2,
+            healthy_threshold: 2,
+        }
+    }
+
+    /// How often the background checker probes each replica.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// How long a single probe may take before it counts as a failure.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Consecutive failed probes before a replica is marked unhealthy.
+    ///
+    /// Values below `1` are treated as `1`.
+    pub fn unhealthy_threshold(mut self, threshold: u32) -> Self {
+        self.unhealthy_threshold = threshold.max(1);
+        self
+    }
+
+    /// Consecutive successful probes before an unhealthy replica is marked healthy again.
+    ///
+    /// Values below `1` are treated as `1`.
+    pub fn healthy_threshold(mut self, threshold: u32) -> Self {
+        self.healthy_threshold = threshold.max(1);
+        self
+    }
+}
+
+impl Default for HealthCheckConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Point-in-time health of a single replica, as returned by
+/// [`DbPools::replica_health`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ReplicaHealth {
+    /// Position of the replica, in the order the replicas were provided.
+    pub index: usize,
+    /// Whether the replica is currently in rotation.
+    pub healthy: bool,
+    /// Number of consecutive failed probes.
+    pub consecutive_failures: u32,
+    /// When the replica was last probed, if it has been probed at all.
+    pub last_checked: Option<Instant>,
+    /// The error from the most recent failed probe, cleared on success.
+    pub last_error: Option<String>,
+}
+
+/// Health tracking embedded in each replica's routing state.
+///
+/// Replicas start out healthy so reads are routed before the first probe.
+#[derive(Debug)]
+pub(crate) struct HealthState {
+    healthy: AtomicBool,
+    consecutive_failures: AtomicU32,
+    consecutive_successes: AtomicU32,
+    last: Mutex<LastProbe>,
+}
+
+#[derive(Debug, Default)]
+struct LastProbe {
+    checked: Option<Instant>,
+    error: Option<String>,
+}
+
+impl HealthState {
+    pub(crate) fn is_healthy(&self) -> bool {
+        self.healthy.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn record_success(&self, config: &HealthCheckConfig) {
+        self.consecutive_failures.store(0, Ordering::Relaxed);
+        let successes = self.consecutive_successes.fetch_add(1, Ordering::Relaxed) + 1;
+        if successes >= config.healthy_threshold {
+            self.healthy.store(true, Ordering::Relaxed);
+        }
+        *self.last.lock().unwrap() = LastProbe {
+            checked: Some(Instant::now()),
+            error: None,
+        };
+    }
+
+    pub(crate) fn record_failure(&self, config: &HealthCheckConfig, error: String) {
+        self.consecutive_successes.store(0, Ordering::Relaxed);
+        let failures = self.consecutive_failures.fetch_add(1, Ordering::Relaxed) + 1;
+        if failures >= config.unhealthy_threshold {
+            self.healthy.store(false, Ordering::Relaxed);
+        }
+        *self.last.lock().unwrap() = LastProbe {
+            checked: Some(Instant::now()),
+            error: Some(error),
+        };
+    }
+
+    fn snapshot(&self, index: usize) -> ReplicaHealth {
+        let last = self.last.lock().unwrap();
+        ReplicaHealth {
+            index,
+            healthy: self.is_healthy(),
+            consecutive_failures: self.consecutive_failures.load(Ordering::Relaxed),
+            last_checked: last.checked,
+            last_error: last.error.clone(),
+        }
+    }
+}
+
+impl Default for HealthState {
+    fn default() -> Self {
+        Self {
+            healthy: AtomicBool::new(true),
+            consecutive_failures: AtomicU32::new(0),
+            consecutive_successes: AtomicU32::new(0),
+            last: Mutex::new(LastProbe::default()),
+        }
+    }
+}
+
+/// Probe every replica once, concurrently, and record the results.
+async fn probe_all(replicas: &Arc<[ReplicaNode]>, config: &HealthCheckConfig) {
+    let mut probes = JoinSet::new();
+    for index in 0..replicas.len() {
+        let replicas = Arc::clone(replicas);
+        let config = config.clone();
+        probes.spawn(async move {
+            let replica = &replicas[index];
+            let started = Instant::now();
+            let probe = sqlx::query("SELECT 1").execute(&replica.pool);
+            match tokio::time::timeout(config.timeout, probe).await {
+                Ok(Ok(_)) => {
+                    replica.latency.record(started.elapsed());
+                    replica.health.record_success(&config);
+                }
+                Ok(Err(e)) => replica.health.record_failure(&config, e.to_string()),
+                Err(_) => replica.health.record_failure(
+                    &config,
+                    format!("health check timed out after {:?}", config.timeout),
+                ),
+            }
+        });
+    }
+    while probes.join_next().await.is_some() {}
+}
+
+impl DbPools {
+    /// Start a background task that periodically probes every replica.
+    ///
+    /// Unhealthy replicas are taken out of rotation. If every replica is
+    /// unhealthy, [`read()`](crate::PoolProvider::read) returns the primary
+    /// until one recovers.
+    ///
+    /// The task stops on its own once every clone of this `DbPools` has been
+    /// dropped. Abort the returned handle to stop it earlier. Must be called
+    /// from within a Tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::{DbPools, HealthCheckConfig};
+    ///
+    /// # async fn example(primary: PgPool, replicas: Vec<PgPool>) {
+    /// let pools = DbPools::with_replicas(primary, replicas);
+    /// let checker = pools.spawn_health_checker(
+    ///     HealthCheckConfig::new().interval(Duration::from_secs(2)),
+    /// );
+    ///
+    /// // ... later, during shutdown
+    /// checker.abort();
+    /// # }
+    /// ```
+    pub fn spawn_health_checker(&self, config: HealthCheckConfig) -> JoinHandle<()> {
+        let interval = config.interval;
+        spawn_monitor(&self.replicas, interval, move |replicas| {
+            let config = config.clone();
+            async move { probe_all(&replicas, &config).await }
+        })
+    }
+
+    /// Probe every replica once and update its health state.
+    ///
+    /// Useful at startup, before traffic arrives, or when driving health
+    /// checks from your own scheduler instead of
+    /// [`spawn_health_checker`](Self::spawn_health_checker).
+    pub async fn check_replica_health(&self, config: &HealthCheckConfig) {
+        probe_all(&self.replicas, config).await;
+    }
+
+    /// Current health of every replica, in the order they were provided.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # fn example(pools: DbPools) {
+    /// for replica in pools.replica_health() {
+    ///     if !replica.healthy {
+    ///         eprintln!("replica {} is down: {:?}", replica.index, replica.last_error);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn replica_health(&self) -> Vec<ReplicaHealth> {
+        self.replicas
+            .iter()
+            .enumerate()
+            .map(|(index, replica)| replica.health.snapshot(index))
+            .collect()
+    }
+}
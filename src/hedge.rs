@@ -0,0 +1,235 @@
+//! Hedged reads: race a slow read against a duplicate on another pool.
+
+use crate::{DbPools, PoolProvider, ReadPool};
+use std::collections::VecDeque;
+use std::future::{poll_fn, Future};
+use std::pin::pin;
+use std::sync::Mutex;
+use std::task::Poll;
+use std::time::{Duration, Instant};
+
+/// Number of recent read latencies kept for the percentile.
+const WINDOW: usize = 256;
+
+/// Settings for [`DbPools::hedged_read_with`].
+///
+/// The duplicate is sent once the first read has taken longer than the
+/// [`percentile`](Self::percentile) of recent hedged read latencies, so
+/// roughly that share of reads never send a second query. Until
+/// [`min_samples`](Self::min_samples) latencies have been recorded,
+/// [`initial_delay`](Self::initial_delay) is used instead.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use sqlx_pool_router::HedgeConfig;
+///
+/// let config = HedgeConfig::new()
+///     .percentile(0.9)
+///     .min_delay(Duration::from_millis(5));
+/// ```
+#[derive(Clone, Debug)]
+pub struct HedgeConfig {
+    percentile: f64,
+    initial_delay: Duration,
+    min_delay: Duration,
+    min_samples: usize,
+    to_primary: bool,
+}
+
+impl HedgeConfig {
+    /// Create a config with the defaults: hedge at the 95th percentile,
+    /// after at least 1ms, using 50ms until 20 latencies have been recorded,
+    /// and fall back to the primary when no other replica is available.
+    pub fn new() -> Self {
+        Self {
+            percentile: 0.95,
+            initial_delay: Duration::from_millis(50),
+            min_delay: Duration::from_millis(1),
+            min_samples: 20,
+            to_primary: true,
+        }
+    }
+
+    /// Latency percentile, between `0.0` and `1.0`, after which the duplicate is sent.
+    pub fn percentile(mut self, percentile: f64) -> Self {
+        self.percentile = percentile.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Delay used while there are too few samples for a percentile.
+    pub fn initial_delay(mut self, delay: Duration) -> Self {
+        self.initial_delay = delay;
+        self
+    }
+
+    /// Lower bound for the delay, so fast reads don't all get duplicated.
+    pub fn min_delay(mut self, delay: Duration) -> Self {
+        self.min_delay = delay;
+        self
+    }
+
+    /// Number of recorded latencies needed before the percentile is used.
+    pub fn min_samples(mut self, samples: usize) -> Self {
+        self.min_samples = samples;
+        self
+    }
+
+    /// Whether the duplicate may go to the primary when there's no other
+    /// replica in rotation. When `false`, such reads aren't hedged.
+    pub fn hedge_to_primary(mut self, enabled: bool) -> Self {
+        self.to_primary = enabled;
+        self
+    }
+}
+
+impl Default for HedgeConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Recent hedged read latencies.
+#[derive(Debug, Default)]
+pub(crate) struct LatencyWindow {
+    samples: Mutex<VecDeque<Duration>>,
+}
+
+impl LatencyWindow {
+    fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap();
+        if samples.len() == WINDOW {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    fn delay(&self, config: &HedgeConfig) -> Duration {
+        let mut sorted: Vec<Duration> = {
+            let samples = self.samples.lock().unwrap();
+            if samples.len() < config.min_samples.max(1) {
+                return config.initial_delay.max(config.min_delay);
+            }
+            samples.iter().copied().collect()
+        };
+        sorted.sort_unstable();
+        let rank = (config.percentile * (sorted.len() - 1) as f64).round() as usize;
+        sorted[rank].max(config.min_delay)
+    }
+}
+
+impl DbPools {
+    /// Run a read with a duplicate sent elsewhere if it's slow.
+    ///
+    /// Same as [`hedged_read_with`](Self::hedged_read_with) with the default
+    /// [`HedgeConfig`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// let name: String = pools
+    ///     .hedged_read(async |pool| {
+    ///         sqlx::query_scalar("SELECT name FROM users WHERE id = $1")
+    ///             .bind(1_i64)
+    ///             .fetch_one(pool)
+    ///             .await
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn hedged_read<T, F>(&self, f: F) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFn(&ReadPool) -> Result<T, sqlx::Error>,
+    {
+        self.hedged_read_with(&HedgeConfig::new(), f).await
+    }
+
+    /// Run a read with a duplicate sent elsewhere if it's slow.
+    ///
+    /// `f` runs on [`read()`](PoolProvider::read). If it hasn't finished
+    /// after [`hedge_delay`](Self::hedge_delay), `f` is also started on
+    /// another in-rotation replica, or on the primary if there is none. The
+    /// first successful result is returned and the other call is dropped,
+    /// which cancels its query. If one call fails, the other is awaited; if
+    /// both fail, the duplicate's error is returned.
+    ///
+    /// Reads that start on the primary, because there are no replicas in
+    /// rotation or inside [`with_primary_reads`](crate::with_primary_reads),
+    /// aren't hedged.
+    ///
+    /// Only hedge idempotent reads: `f` may run twice, and a dropped query
+    /// may have its connection closed rather than returned to the pool.
+    pub async fn hedged_read_with<T, F>(&self, config: &HedgeConfig, f: F) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFn(&ReadPool) -> Result<T, sqlx::Error>,
+    {
+        let first_pool = self.read();
+        let Some(first_index) = self.replica_index(first_pool) else {
+            return f(first_pool).await;
+        };
+
+        let started = Instant::now();
+        let mut first = pin!(self.observe(first_pool, f(first_pool)));
+        let delay = self.hedge_delay(config);
+        if let Ok(result) = tokio::time::timeout(delay, first.as_mut()).await {
+            self.read_stats.hedge_latency.record(started.elapsed());
+            return result;
+        }
+
+        let second_pool = match self.untried_replica(&[first_index]) {
+            Some(pool) => pool,
+            None if config.to_primary => ReadPool::new(self.primary.current()),
+            None => return first.await,
+        };
+        let second = pin!(self.observe(second_pool, f(second_pool)));
+        let result = first_success(first, second).await;
+        self.read_stats.hedge_latency.record(started.elapsed());
+        result
+    }
+
+    /// How long [`hedged_read_with`](Self::hedged_read_with) currently waits
+    /// before sending a duplicate, based on recent latencies.
+    pub fn hedge_delay(&self, config: &HedgeConfig) -> Duration {
+        self.read_stats.hedge_latency.delay(config)
+    }
+}
+
+/// Poll both futures until one succeeds, or both have failed.
+async fn first_success<T, A, B>(
+    mut a: std::pin::Pin<&mut A>,
+    mut b: std::pin::Pin<&mut B>,
+) -> Result<T, sqlx::Error>
+where
+    A: Future<Output = Result<T, sqlx::Error>>,
+    B: Future<Output = Result<T, sqlx::Error>>,
+{
+    let mut a_failed = None;
+    let mut b_failed = None;
+    poll_fn(|cx| {
+        if a_failed.is_none() {
+            match a.as_mut().poll(cx) {
+                Poll::Ready(Ok(value)) => return Poll::Ready(Ok(value)),
+                Poll::Ready(Err(e)) => a_failed = Some(e),
+                Poll::Pending => {}
+            }
+        }
+        if b_failed.is_none() {
+            match b.as_mut().poll(cx) {
+                Poll::Ready(Ok(value)) => return Poll::Ready(Ok(value)),
+                Poll::Ready(Err(e)) => b_failed = Some(e),
+                Poll::Pending => {}
+            }
+        }
+        match (&a_failed, &b_failed) {
+            (Some(_), Some(_)) => Poll::Ready(Err(())),
+            _ => Poll::Pending,
+        }
+    })
+    .await
+    .map_err(|()| b_failed.take().or(a_failed.take()).unwrap())
+}
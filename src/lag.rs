@@ -0,0 +1,306 @@
+//! Replication lag sampling for lag-aware read routing.
+
+use crate::replica::{spawn_monitor, ReplicaNode};
+use crate::DbPools;
+use sqlx::PgPool;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::{JoinHandle, JoinSet};
+
+/// Time since the last replayed transaction, or zero when the replica is
+/// streaming from the primary and has replayed everything it received (an
+/// idle primary is not lag). A replica whose WAL receiver is down has also
+/// replayed everything it received, so it is judged by the last replay
+/// time. Roles without `pg_read_all_stats` can't see the receiver's status,
+/// only whether it is running. Servers that are not in recovery report
+/// zero.
+const TIME_LAG_QUERY: &str = "\
+    SELECT CASE \
+        WHEN NOT pg_is_in_recovery() THEN 0 \
+        WHEN pg_last_wal_receive_lsn() = pg_last_wal_replay_lsn() \
+            AND EXISTS (SELECT 1 FROM pg_stat_wal_receiver \
+                WHERE COALESCE(status = 'streaming', pid IS NOT NULL)) THEN 0 \
+        ELSE COALESCE(EXTRACT(EPOCH FROM now() - pg_last_xact_replay_timestamp()), 0) \
+    END::float8";
+
+/// Bytes of WAL between the primary's position (`$1`) and the replica's
+/// replay position. Servers that are not in recovery report zero.
+const BYTE_LAG_QUERY: &str = "\
+    SELECT pg_wal_lsn_diff($1::pg_lsn, COALESCE(pg_last_wal_replay_lsn(), $1::pg_lsn))::float8";
+
+/// Settings for replication-lag-aware routing.
+///
+/// Replicas are sampled on an interval. A replica whose lag exceeds
+/// [`max_lag`](Self::max_lag), or [`max_lag_bytes`](Self::max_lag_bytes) when
+/// set, is skipped by [`read()`](crate::PoolProvider::read). When every
+/// replica is too far behind, reads go to the primary.
+///
+/// Time lag is measured with `pg_last_xact_replay_timestamp()`, and is zero
+/// while a replica streaming from the primary has replayed everything it
+/// received. A replica that has lost its connection to the primary keeps
+/// aging instead. Byte lag compares the primary's `pg_current_wal_lsn()`
+/// with each replica's `pg_last_wal_replay_lsn()`.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use sqlx_pool_router::ReplicationLagConfig;
+///
+/// // Skip replicas more than 500ms or 16MB of WAL behind the primary
+/// let config = ReplicationLagConfig::new(Duration::from_millis(500))
+///     .max_lag_bytes(16 * 1024 * 1024)
+///     .interval(Duration::from_secs(1));
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReplicationLagConfig {
+    max_lag: Duration,
+    max_lag_bytes: Option<u64>,
+    interval: Duration,
+    timeout: Duration,
+}
+
+impl ReplicationLagConfig {
+    /// Create a config that skips replicas more than `max_lag` behind.
+    ///
+    /// Samples every second and times out a sample after 2 seconds.
+    pub fn new(max_lag: Duration) -> Self {
+        Self {
+            max_lag,
+            max_lag_bytes: None,
+            interval: Duration::from_secs(1),
+            timeout: Duration::from_secs(2),
+        }
+    }
+
+    /// Maximum time lag before a replica is skipped.
+    pub fn max_lag(mut self, max_lag: Duration) -> Self {
+        self.max_lag = max_lag;
+        self
+    }
+
+    /// Also skip replicas more than this many bytes of WAL behind the primary.
+    pub fn max_lag_bytes(mut self, max_lag_bytes: u64) -> Self {
+        self.max_lag_bytes = Some(max_lag_bytes);
+        self
+    }
+
+    /// How often the background monitor samples each replica.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// How long a single sample may take before it counts as a failure.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+}
+
+/// Most recent lag sample for a single replica, as returned by
+/// [`DbPools::replica_lag`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReplicaLag {
+    /// Position of the replica, in the order the replicas were provided.
+    pub index: usize,
+    /// Time since the last replayed transaction, if sampled successfully.
+    pub lag: Option<Duration>,
+    /// Bytes of WAL behind the primary, if byte lag is being measured.
+    pub lag_bytes: Option<u64>,
+    /// Whether the replica is within the configured limits and in rotation.
+    pub within_limit: bool,
+    /// When the replica was last sampled, if it has been sampled at all.
+    pub sampled_at: Option<Instant>,
+    /// The error from the most recent failed sample, cleared on success.
+    pub last_error: Option<String>,
+}
+
+/// Lag tracking embedded in each replica's routing state.
+///
+/// Replicas start out within limits so reads are routed before the first
+/// sample. A replica whose lag can't be sampled is treated as too far behind.
+#[derive(Debug)]
+pub(crate) struct LagState {
+    within_limit: AtomicBool,
+    last: Mutex<LastSample>,
+}
+
+#[derive(Debug, Default)]
+struct LastSample {
+    lag: Option<Duration>,
+    lag_bytes: Option<u64>,
+    sampled_at: Option<Instant>,
+    error: Option<String>,
+}
+
+impl LagState {
+    pub(crate) fn is_within_limit(&self) -> bool {
+        self.within_limit.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn lag(&self) -> Option<Duration> {
+        self.last.lock().unwrap().lag
+    }
+
+    pub(crate) fn record(
+        &self,
+        config: &ReplicationLagConfig,
+        lag: Duration,
+        lag_bytes: Option<u64>,
+    ) {
+        let within_limit = lag <= config.max_lag
+            && match (config.max_lag_bytes, lag_bytes) {
+                (Some(max), Some(bytes)) => bytes <= max,
+                _ => true,
+            };
+        self.within_limit.store(within_limit, Ordering::Relaxed);
+        *self.last.lock().unwrap() = LastSample {
+            lag: Some(lag),
+            lag_bytes,
+            sampled_at: Some(Instant::now()),
+            error: None,
+        };
+    }
+
+    fn record_failure(&self, error: String) {
+        self.within_limit.store(false, Ordering::Relaxed);
+        *self.last.lock().unwrap() = LastSample {
+            sampled_at: Some(Instant::now()),
+            error: Some(error),
+            ..LastSample::default()
+        };
+    }
+
+    fn snapshot(&self, index: usize) -> ReplicaLag {
+        let last = self.last.lock().unwrap();
+        ReplicaLag {
+            index,
+            lag: last.lag,
+            lag_bytes: last.lag_bytes,
+            within_limit: self.is_within_limit(),
+            sampled_at: last.sampled_at,
+            last_error: last.error.clone(),
+        }
+    }
+}
+
+impl Default for LagState {
+    fn default() -> Self {
+        Self {
+            within_limit: AtomicBool::new(true),
+            last: Mutex::new(LastSample::default()),
+        }
+    }
+}
+
+/// Sample time lag and, when `primary_lsn` is known, byte lag on one replica.
+async fn sample(
+    pool: &PgPool,
+    primary_lsn: Option<&str>,
+) -> Result<(Duration, Option<u64>), sqlx::Error> {
+    let seconds: f64 = sqlx::query_scalar(TIME_LAG_QUERY).fetch_one(pool).await?;
+    let lag_bytes = match primary_lsn {
+        Some(lsn) => {
+            let bytes: f64 = sqlx::query_scalar(BYTE_LAG_QUERY)
+                .bind(lsn)
+                .fetch_one(pool)
+                .await?;
+            // The replica may be sampled after the primary and appear ahead
+            Some(bytes.max(0.0) as u64)
+        }
+        None => None,
+    };
+    Ok((Duration::from_secs_f64(seconds.max(0.0)), lag_bytes))
+}
+
+/// Sample every replica once, concurrently, and record the results.
+async fn sample_all(
+    primary: &PgPool,
+    replicas: &Arc<[ReplicaNode]>,
+    config: &ReplicationLagConfig,
+) {
+    // Without the primary's position only time lag can be judged this round
+    let primary_lsn = match config.max_lag_bytes {
+        Some(_) => {
+            let lsn = sqlx::query_scalar::<_, String>("SELECT pg_current_wal_lsn()::text")
+                .fetch_one(primary);
+            tokio::time::timeout(config.timeout, lsn)
+                .await
+                .ok()
+                .and_then(Result::ok)
+        }
+        None => None,
+    };
+
+    let mut samples = JoinSet::new();
+    for index in 0..replicas.len() {
+        let replicas = Arc::clone(replicas);
+        let config = config.clone();
+        let primary_lsn = primary_lsn.clone();
+        samples.spawn(async move {
+            let replica = &replicas[index];
+            let sampled = sample(&replica.pool, primary_lsn.as_deref());
+            match tokio::time::timeout(config.timeout, sampled).await {
+                Ok(Ok((lag, lag_bytes))) => replica.lag.record(&config, lag, lag_bytes),
+                Ok(Err(e)) => replica.lag.record_failure(e.to_string()),
+                Err(_) => replica
+                    .lag
+                    .record_failure(format!("lag sample timed out after {:?}", config.timeout)),
+            }
+        });
+    }
+    while samples.join_next().await.is_some() {}
+}
+
+impl DbPools {
+    /// Start a background task that periodically samples replication lag.
+    ///
+    /// Replicas behind the configured limit are skipped by
+    /// [`read()`](crate::PoolProvider::read). If every replica is too far
+    /// behind, reads go to the primary until one catches up.
+    ///
+    /// The task stops on its own once every clone of this `DbPools` has been
+    /// dropped. Abort the returned handle to stop it earlier. Must be called
+    /// from within a Tokio runtime.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::{DbPools, ReplicationLagConfig};
+    ///
+    /// # async fn example(primary: PgPool, replicas: Vec<PgPool>) {
+    /// let pools = DbPools::with_replicas(primary, replicas);
+    /// pools.spawn_lag_monitor(ReplicationLagConfig::new(Duration::from_millis(500)));
+    /// # }
+    /// ```
+    pub fn spawn_lag_monitor(&self, config: ReplicationLagConfig) -> JoinHandle<()> {
+        let primary = self.primary.clone();
+        let interval = config.interval;
+        spawn_monitor(&self.replicas, interval, move |replicas| {
+            let primary = primary.clone();
+            let config = config.clone();
+            async move { sample_all(primary.current(), &replicas, &config).await }
+        })
+    }
+
+    /// Sample replication lag on every replica once and update routing.
+    ///
+    /// Useful at startup, or when driving lag sampling from your own
+    /// scheduler instead of [`spawn_lag_monitor`](Self::spawn_lag_monitor).
+    pub async fn sample_replication_lag(&self, config: &ReplicationLagConfig) {
+        sample_all(self.primary.current(), &self.replicas, config).await;
+    }
+
+    /// Most recent lag sample for every replica, in the order they were provided.
+    pub fn replica_lag(&self) -> Vec<ReplicaLag> {
+        self.replicas
+            .iter()
+            .enumerate()
+            .map(|(index, replica)| replica.lag.snapshot(index))
+            .collect()
+    }
+}
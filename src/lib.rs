@@ -9,9 +9,31 @@
 //! ## Features
 //!
 //! - **Zero-cost abstraction**: Trait-based design with no runtime overhead
-//! - **Type-safe routing**: Compile-time guarantees for read/write pool separation
+//! - **Type-safe routing**: `read()` returns a [`ReadPool`] and `write()` a [`WritePool`], so signatures say which access they need
 //! - **Backward compatible**: `PgPool` implements `PoolProvider` for seamless integration
 //! - **Flexible**: Use single pool or separate primary/replica pools
+//! - **Multiple replicas**: Spread reads with round-robin, random, least-busy or least-latency [`LoadBalance`]
+//! - **Pluggable routing**: Bring your own replica selection by implementing [`RoutingPolicy`]
+//! - **Zone affinity**: [`ZoneAffinity`] keeps reads on replicas in the local availability zone, falling back to other zones, then the primary
+//! - **Weighted replicas**: Give larger replicas a bigger share of reads, adjustable at runtime
+//! - **Health checking**: Optional background probes take failed replicas out of rotation
+//! - **Lag-aware routing**: Skip replicas that are too far behind the primary
+//! - **Read-your-writes**: Route reads to replicas that have replayed a given WAL position
+//! - **Consistency tokens**: Carry "at least this fresh" markers between services (`serde` feature)
+//! - **Affinity keys**: [`DbPools::read_for`] maps a user or tenant ID to the same replica with rendezvous hashing
+//! - **Sticky sessions**: Keep a session's reads on the primary for a window after it writes
+//! - **Scoped overrides**: Force every read in an async block to the primary with [`with_primary_reads`]
+//! - **Primary discovery**: Find the writable host among candidates and follow failovers
+//! - **Topology validation**: Catch swapped or mismatched pool URLs at startup with [`DbPools::validate`]
+//! - **Role-aware transactions**: [`PoolProvider::begin_read`] opens a `READ ONLY` transaction on the read pool
+//! - **Recovery conflict retries**: [`DbPools::retry_read`] re-runs reads cancelled by a standby on another replica or the primary
+//! - **Hedged reads**: [`DbPools::hedged_read`] sends a duplicate to another pool when a read is slower than usual
+//! - **Circuit breakers**: [`DbPools::circuit_breaker`] takes replicas that keep failing or running slow out of rotation for a cooldown
+//! - **Primary spillover**: [`DbPools::spill_to_primary`] sends reads to the primary while a replica pool is saturated and the primary has idle connections to spare
+//! - **Replica fallback**: [`DbPools::read_with_fallback`] retries a read on the primary when a replica can't be reached
+//! - **Retrying write transactions**: [`DbPools::write_tx`] commits, rolls back and retries serialization failures and deadlocks
+//! - **Executor handles**: [`PoolProvider::read_executor`] and [`PoolProvider::write_executor`] implement `sqlx::Executor` and `sqlx::Acquire`
+//! - **Automatic routing**: Pass [`PoolProvider::auto`] to sqlx and let the SQL decide the pool (full parser with the `sql-parser` feature)
 //! - **Test helpers**: [`TestDbPools`] for testing with `#[sqlx::test]`
 //! - **Well-tested**: Comprehensive test suite with replica routing verification
 //!
@@ -74,11 +96,252 @@
 //! │   DbPools   │
 //! └──────┬──────┘
 //!        │
-//!   ┌────┴────┐
-//!   ↓         ↓
-//! ┌─────┐  ┌─────────┐
-//! │Primary│  │ Replica │ (optional)
-//! └─────┘  └─────────┘
+//!   ┌────┴─────────────┐
+//!   ↓                  ↓
+//! ┌───────┐  ┌─────────────────────┐
+//! │Primary│  │ Replicas (0..n)     │
+//! └───────┘  │ selected by         │
+//!            │ LoadBalance strategy│
+//!            └─────────────────────┘
+//! ```
+//!
+//! ## Multiple Replicas
+//!
+//! ```rust,no_run
+//! use sqlx::PgPool;
+//! use sqlx_pool_router::{DbPools, LoadBalance, PoolProvider};
+//!
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let primary = PgPool::connect("postgresql://primary-host/mydb").await?;
+//! let replicas = vec![
+//!     PgPool::connect("postgresql://replica-1/mydb").await?,
+//!     PgPool::connect("postgresql://replica-2/mydb").await?,
+//!     PgPool::connect("postgresql://replica-3/mydb").await?,
+//! ];
+//!
+//! let pools = DbPools::with_replicas(primary, replicas).load_balance(LoadBalance::LeastBusy);
+//!
+//! // Each read picks one of the three replicas
+//! let count: (i64,) = sqlx::query_as("SELECT COUNT(*) FROM users")
+//!     .fetch_one(pools.read())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Health Checking
+//!
+//! A background task can probe each replica and take failed ones out of
+//! rotation. When no replica is healthy, reads go to the primary.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use sqlx::PgPool;
+//! use sqlx_pool_router::{DbPools, HealthCheckConfig};
+//!
+//! # async fn example(primary: PgPool, replicas: Vec<PgPool>) {
+//! let pools = DbPools::with_replicas(primary, replicas);
+//! pools.spawn_health_checker(HealthCheckConfig::new().interval(Duration::from_secs(2)));
+//!
+//! for replica in pools.replica_health() {
+//!     println!("replica {}: healthy = {}", replica.index, replica.healthy);
+//! }
+//! # }
+//! ```
+//!
+//! ## Lag-Aware Routing
+//!
+//! Replicas that fall too far behind the primary can be skipped. When every
+//! replica is behind, reads go to the primary.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use sqlx::PgPool;
+//! use sqlx_pool_router::{DbPools, ReplicationLagConfig};
+//!
+//! # async fn example(primary: PgPool, replicas: Vec<PgPool>) {
+//! let pools = DbPools::with_replicas(primary, replicas);
+//! pools.spawn_lag_monitor(
+//!     ReplicationLagConfig::new(Duration::from_millis(500)).max_lag_bytes(16 * 1024 * 1024),
+//! );
+//! # }
+//! ```
+//!
+//! ## Read-Your-Writes
+//!
+//! Capture the primary's WAL position after a write, then read from a replica
+//! that has replayed at least that far. If none catches up in time, the read
+//! goes to the primary.
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use sqlx_pool_router::{DbPools, PoolProvider};
+//!
+//! # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+//! sqlx::query("INSERT INTO users (name) VALUES ('Alice')")
+//!     .execute(pools.write())
+//!     .await?;
+//! let lsn = pools.current_wal_lsn().await?;
+//!
+//! let users: Vec<(String,)> = sqlx::query_as("SELECT name FROM users")
+//!     .fetch_all(pools.read_after_lsn(lsn, Duration::from_millis(100)).await)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! To carry the same guarantee across services, send a [`ConsistencyToken`]
+//! (from [`DbPools::consistency_token`]) along with the request and route the
+//! downstream read with [`DbPools::read_consistent`]. Enable the `serde`
+//! feature to embed tokens in serialized messages.
+//!
+//! For a simpler approach without WAL tracking, a [`Session`] reads from the
+//! primary for a fixed window after each of its writes:
+//!
+//! ```rust,no_run
+//! use std::time::Duration;
+//! use sqlx_pool_router::{DbPools, PoolProvider};
+//!
+//! # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+//! let session = pools.session(Duration::from_secs(2));
+//!
+//! sqlx::query("INSERT INTO users (name) VALUES ('Alice')")
+//!     .execute(session.write())
+//!     .await?;
+//!
+//! // Reads from the primary for the next 2 seconds
+//! let users: Vec<(String,)> = sqlx::query_as("SELECT name FROM users")
+//!     .fetch_all(session.read())
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! To send every read in a block of async code to the primary, such as a
+//! request handler, wrap it in [`with_primary_reads`]:
+//!
+//! ```rust,no_run
+//! use sqlx_pool_router::{with_primary_reads, DbPools, PoolProvider};
+//!
+//! # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+//! let count: i64 = with_primary_reads(async {
+//!     sqlx::query_scalar("SELECT COUNT(*) FROM users")
+//!         .fetch_one(pools.read()) // the primary
+//!         .await
+//! })
+//! .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Primary Discovery and Failover
+//!
+//! With a cluster manager such as Patroni, the primary can move between
+//! hosts. [`DbPools::discover`] takes a pool per candidate host and picks the
+//! one that accepts writes. [`DbPools::write_with_failover`] re-discovers the
+//! primary when a write fails because the host became a read-only standby
+//! or went away, then retries the write on the new primary. The old primary
+//! may have committed the write before it went away, so the write must be
+//! safe to run twice.
+//!
+//! ```rust,no_run
+//! use sqlx::PgPool;
+//! use sqlx_pool_router::DbPools;
+//!
+//! # async fn example(event_id: i64) -> Result<(), sqlx::Error> {
+//! let candidates = vec![
+//!     PgPool::connect_lazy("postgresql://db-1/app")?,
+//!     PgPool::connect_lazy("postgresql://db-2/app")?,
+//! ];
+//! let pools = DbPools::discover(candidates, Vec::<PgPool>::new()).await?;
+//!
+//! pools
+//!     .write_with_failover(async |pool| {
+//!         sqlx::query("INSERT INTO events (id) VALUES ($1) ON CONFLICT DO NOTHING")
+//!             .bind(event_id)
+//!             .execute(pool)
+//!             .await
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Transactions
+//!
+//! [`PoolProvider::begin_write`] opens a transaction on the primary.
+//! [`PoolProvider::begin_read`] opens one on the read pool and runs
+//! `SET TRANSACTION READ ONLY`, so multi-statement reads get both the right
+//! pool and server-enforced read-only semantics. Pass [`TransactionOptions`]
+//! to `begin_read_with` or `begin_write_with` for an isolation level or
+//! `DEFERRABLE`:
+//!
+//! ```rust,no_run
+//! use sqlx_pool_router::{DbPools, IsolationLevel, PoolProvider, TransactionOptions};
+//!
+//! # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+//! let snapshot = TransactionOptions::new().isolation(IsolationLevel::RepeatableRead);
+//! let mut tx = pools.begin_read_with(snapshot).await?;
+//! let users: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM users")
+//!     .fetch_one(&mut *tx)
+//!     .await?;
+//! let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+//!     .fetch_one(&mut *tx)
+//!     .await?;
+//! tx.commit().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! For read-modify-write logic, [`DbPools::write_tx`] runs a closure in a
+//! transaction on the primary. It commits on `Ok`, rolls back on `Err`, and
+//! runs the transaction again after serialization failures (`40001`) and
+//! deadlocks (`40P01`). Configure attempts, backoff and isolation level
+//! with [`WriteTxConfig`]:
+//!
+//! ```rust,no_run
+//! use sqlx_pool_router::{DbPools, IsolationLevel, WriteTxConfig};
+//!
+//! # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+//! let config = WriteTxConfig::new()
+//!     .isolation(IsolationLevel::Serializable)
+//!     .max_attempts(5);
+//! pools
+//!     .write_tx_with(&config, async |tx| {
+//!         sqlx::query("UPDATE accounts SET balance = balance - 10 WHERE id = 1")
+//!             .execute(&mut **tx)
+//!             .await?;
+//!         sqlx::query("UPDATE accounts SET balance = balance + 10 WHERE id = 2")
+//!             .execute(&mut **tx)
+//!             .await?;
+//!         Ok(())
+//!     })
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! ## Automatic Routing
+//!
+//! When call sites shouldn't have to choose, pass [`PoolProvider::auto`] as
+//! the executor. It [`classify`]s each statement and sends plain `SELECT`,
+//! `WITH ... SELECT` and `SHOW` queries to `read()`, and DML, DDL,
+//! `SELECT ... FOR UPDATE`, `nextval` and writable CTEs to `write()`.
+//! Enable the `sql-parser` feature to classify from a parsed syntax tree
+//! rather than by keyword scanning:
+//!
+//! ```rust,no_run
+//! use sqlx_pool_router::{DbPools, PoolProvider};
+//!
+//! # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+//! let names: Vec<String> = sqlx::query_scalar("SELECT name FROM users")
+//!     .fetch_all(pools.auto()) // a replica
+//!     .await?;
+//! sqlx::query("DELETE FROM sessions WHERE expires_at < now()")
+//!     .execute(pools.auto()) // the primary
+//!     .await?;
+//! # Ok(())
+//! # }
 //! ```
 //!
 //! ## Generic Programming
@@ -133,8 +396,62 @@
 //!
 //! This catches routing bugs immediately without needing a real replica database.
 
-use sqlx::PgPool;
+#![deny(unsafe_op_in_unsafe_fn, clippy::undocumented_unsafe_blocks)]
+
+use sqlx::{PgPool, Postgres, Transaction};
+use std::future::Future;
 use std::ops::Deref;
+use std::sync::Arc;
+use std::time::Duration;
+
+mod affinity;
+mod balance;
+mod capability;
+mod circuit;
+mod classify;
+mod consistency;
+mod context;
+mod executor;
+mod failover;
+mod health;
+mod hedge;
+mod lag;
+mod replica;
+mod retry;
+mod routing;
+mod session;
+mod spill;
+mod topology;
+mod transaction;
+mod zone;
+
+pub use affinity::AffinityKey;
+pub use balance::{LeastBusy, LeastLatency, LoadBalance, Random, RoundRobin};
+pub use capability::{ReadPool, WritePool};
+pub use circuit::{CircuitBreakerConfig, CircuitState};
+pub use classify::{classify, StatementKind};
+pub use consistency::{ConsistencyToken, Lsn, ParseLsnError, ParseTokenError};
+pub use context::{primary_reads_forced, with_primary_reads};
+pub use executor::{AutoRouter, ReadExecutor, WriteExecutor};
+pub use failover::is_failover_error;
+pub use health::{HealthCheckConfig, ReplicaHealth};
+pub use hedge::HedgeConfig;
+pub use lag::{ReplicaLag, ReplicationLagConfig};
+pub use replica::Replica;
+pub use retry::{
+    is_connection_error, is_recovery_conflict, is_transaction_conflict, ReadFallbackConfig,
+    ReadRetryConfig, WriteTxConfig,
+};
+pub use routing::{Candidate, RoutingPolicy};
+pub use session::Session;
+pub use spill::SpilloverConfig;
+pub use topology::{PoolRole, TopologyError};
+pub use transaction::{IsolationLevel, TransactionOptions};
+pub use zone::ZoneAffinity;
+
+use failover::PrimarySet;
+use replica::ReplicaNode;
+use retry::ReadStats;
 
 /// Trait for providing database pools with read/write routing.
 ///
@@ -171,7 +488,7 @@ use std::ops::Deref;
 ///
 /// ```
 /// use sqlx::PgPool;
-/// use sqlx_pool_router::PoolProvider;
+/// use sqlx_pool_router::{PoolProvider, ReadPool, WritePool};
 ///
 /// #[derive(Clone)]
 /// struct MyPools {
@@ -180,12 +497,12 @@ use std::ops::Deref;
 /// }
 ///
 /// impl PoolProvider for MyPools {
-///     fn read(&self) -> &PgPool {
-///         self.replica.as_ref().unwrap_or(&self.primary)
+///     fn read(&self) -> &ReadPool {
+///         ReadPool::new(self.replica.as_ref().unwrap_or(&self.primary))
 ///     }
 ///
-///     fn write(&self) -> &PgPool {
-///         &self.primary
+///     fn write(&self) -> &WritePool {
+///         WritePool::new(&self.primary)
 ///     }
 /// }
 /// ```
@@ -194,18 +511,99 @@ pub trait PoolProvider: Clone + Send + Sync + 'static {
     ///
     /// May return a read replica for load distribution, or fall back to
     /// the primary pool if no replica is configured.
-    fn read(&self) -> &PgPool;
+    fn read(&self) -> &ReadPool;
 
     /// Get a pool for write operations.
     ///
     /// Should always return the primary pool to ensure ACID guarantees
     /// and read-after-write consistency.
-    fn write(&self) -> &PgPool;
+    fn write(&self) -> &WritePool;
+
+    /// Get an executor that picks the pool for each query from its SQL.
+    ///
+    /// See [`AutoRouter`] for how statements are classified.
+    fn auto(&self) -> AutoRouter<'_, Self> {
+        AutoRouter::new(self)
+    }
+
+    /// Get an executor handle for the read pool.
+    ///
+    /// Implements [`sqlx::Executor`] and [`sqlx::Acquire`], for code that is
+    /// generic over where its connection comes from.
+    fn read_executor(&self) -> ReadExecutor<'_, Self> {
+        ReadExecutor::new(self)
+    }
+
+    /// Get an executor handle for the write pool.
+    ///
+    /// Implements [`sqlx::Executor`] and [`sqlx::Acquire`], for code that is
+    /// generic over where its connection comes from.
+    fn write_executor(&self) -> WriteExecutor<'_, Self> {
+        WriteExecutor::new(self)
+    }
+
+    /// Open a read-only transaction on the read pool.
+    ///
+    /// Runs `SET TRANSACTION READ ONLY`, so the server rejects writes even
+    /// when the read pool is the primary. Use it for multi-statement reads
+    /// that must see one snapshot, together with
+    /// [`IsolationLevel::RepeatableRead`] via [`begin_read_with`](Self::begin_read_with).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::{DbPools, IsolationLevel, PoolProvider, TransactionOptions};
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// let options = TransactionOptions::new().isolation(IsolationLevel::RepeatableRead);
+    /// let mut tx = pools.begin_read_with(options).await?;
+    /// let orders: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+    ///     .fetch_one(&mut *tx)
+    ///     .await?;
+    /// let revenue: i64 = sqlx::query_scalar("SELECT COALESCE(SUM(total), 0)::int8 FROM orders")
+    ///     .fetch_one(&mut *tx)
+    ///     .await?;
+    /// tx.commit().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    fn begin_read(
+        &self,
+    ) -> impl Future<Output = Result<Transaction<'static, Postgres>, sqlx::Error>> + Send {
+        self.begin_read_with(TransactionOptions::new())
+    }
+
+    /// Open a read-only transaction with an isolation level and `DEFERRABLE`.
+    ///
+    /// Like [`begin_read`](Self::begin_read). Hot standbys can't run
+    /// `SERIALIZABLE` transactions, so with [`IsolationLevel::Serializable`]
+    /// the transaction is opened on the primary instead.
+    fn begin_read_with(
+        &self,
+        options: TransactionOptions,
+    ) -> impl Future<Output = Result<Transaction<'static, Postgres>, sqlx::Error>> + Send {
+        transaction::begin_read(self, options)
+    }
+
+    /// Open a transaction on the write pool.
+    fn begin_write(
+        &self,
+    ) -> impl Future<Output = Result<Transaction<'static, Postgres>, sqlx::Error>> + Send {
+        self.begin_write_with(TransactionOptions::new())
+    }
+
+    /// Open a transaction on the write pool with an isolation level.
+    fn begin_write_with(
+        &self,
+        options: TransactionOptions,
+    ) -> impl Future<Output = Result<Transaction<'static, Postgres>, sqlx::Error>> + Send {
+        transaction::begin_write(self, options)
+    }
 }
 
 /// Database pool abstraction supporting read replicas.
 ///
-/// Wraps primary and optional replica pools, providing methods for
+/// Wraps a primary pool and any number of replica pools, providing methods for
 /// explicit read/write routing while maintaining backwards compatibility
 /// through `Deref<Target = PgPool>`.
 ///
@@ -249,10 +647,55 @@ pub trait PoolProvider: Clone + Send + Sync + 'static {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// ## Multiple Replicas
+///
+/// ```rust,no_run
+/// use sqlx::PgPool;
+/// use sqlx_pool_router::{DbPools, LoadBalance};
+///
+/// # async fn example() -> Result<(), sqlx::Error> {
+/// let primary = PgPool::connect("postgresql://primary/db").await?;
+/// let replicas = vec![
+///     PgPool::connect("postgresql://replica-1/db").await?,
+///     PgPool::connect("postgresql://replica-2/db").await?,
+/// ];
+///
+/// let pools = DbPools::with_replicas(primary, replicas).load_balance(LoadBalance::Random);
+/// assert_eq!(pools.replicas().len(), 2);
+/// # Ok(())
+/// # }
+/// ```
+///
+/// ## Weighted Replicas
+///
+/// ```rust,no_run
+/// use sqlx::PgPool;
+/// use sqlx_pool_router::{DbPools, Replica};
+///
+/// # async fn example() -> Result<(), sqlx::Error> {
+/// let primary = PgPool::connect("postgresql://primary/db").await?;
+/// let large = PgPool::connect("postgresql://replica-large/db").await?;
+/// let small = PgPool::connect("postgresql://replica-small/db").await?;
+///
+/// let pools = DbPools::with_replicas(
+///     primary,
+///     vec![Replica::new(large).weight(4), Replica::new(small).weight(1)],
+/// );
+///
+/// // Shift more traffic to the small replica without rebuilding the pools
+/// pools.set_replica_weight(1, 2);
+/// # Ok(())
+/// # }
+/// ```
 #[derive(Clone, Debug)]
 pub struct DbPools {
-    primary: PgPool,
-    replica: Option<PgPool>,
+    primary: Arc<PrimarySet>,
+    replicas: Arc<[ReplicaNode]>,
+    routing: Arc<dyn RoutingPolicy>,
+    read_stats: Arc<ReadStats>,
+    breaker: Option<Arc<CircuitBreakerConfig>>,
+    spillover: Option<Arc<SpilloverConfig>>,
 }
 
 impl DbPools {
@@ -274,10 +717,7 @@ impl DbPools {
     /// # }
     /// ```
     pub fn new(primary: PgPool) -> Self {
-        Self {
-            primary,
-            replica: None,
-        }
+        Self::with_replicas(primary, Vec::<PgPool>::new())
     }
 
     /// Create a new DbPools with primary and replica pools.
@@ -307,15 +747,138 @@ impl DbPools {
     /// # }
     /// ```
     pub fn with_replica(primary: PgPool, replica: PgPool) -> Self {
+        Self::with_replicas(primary, vec![replica])
+    }
+
+    /// Create a new DbPools with a primary pool and any number of replica pools.
+    ///
+    /// Read operations are spread across the replicas using the configured
+    /// [`LoadBalance`] strategy (round-robin by default). With an empty
+    /// `replicas` list this behaves like [`new`](Self::new).
+    ///
+    /// Replicas can be plain [`PgPool`]s or [`Replica`]s carrying per-replica
+    /// options such as a [`weight`](Replica::weight).
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example() -> Result<(), sqlx::Error> {
+    /// let primary = PgPool::connect("postgresql://primary/db").await?;
+    /// let replicas = vec![
+    ///     PgPool::connect("postgresql://replica-1/db").await?,
+    ///     PgPool::connect("postgresql://replica-2/db").await?,
+    ///     PgPool::connect("postgresql://replica-3/db").await?,
+    /// ];
+    ///
+    /// let pools = DbPools::with_replicas(primary, replicas);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_replicas<R>(primary: PgPool, replicas: impl IntoIterator<Item = R>) -> Self
+    where
+        R: Into<Replica>,
+    {
+        Self::from_parts(PrimarySet::new(vec![primary], 0), replicas)
+    }
+
+    fn from_parts<R>(primary: PrimarySet, replicas: impl IntoIterator<Item = R>) -> Self
+    where
+        R: Into<Replica>,
+    {
         Self {
-            primary,
-            replica: Some(replica),
+            primary: Arc::new(primary),
+            replicas: replicas
+                .into_iter()
+                .map(|replica| ReplicaNode::from(replica.into()))
+                .collect(),
+            routing: LoadBalance::default().policy(),
+            read_stats: Arc::default(),
+            breaker: None,
+            spillover: None,
+        }
+    }
+
+    /// Set the strategy used to spread reads across replicas.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::{DbPools, LoadBalance};
+    ///
+    /// # async fn example(primary: PgPool, replicas: Vec<PgPool>) {
+    /// let pools = DbPools::with_replicas(primary, replicas).load_balance(LoadBalance::LeastBusy);
+    /// # }
+    /// ```
+    pub fn load_balance(mut self, strategy: LoadBalance) -> Self {
+        self.routing = strategy.policy();
+        self
+    }
+
+    /// The configured replica pools, in the order they were provided.
+    pub fn replicas(&self) -> impl ExactSizeIterator<Item = &PgPool> {
+        self.replicas.iter().map(|replica| &replica.pool)
+    }
+
+    /// The replica pool at `index`, in the order they were provided.
+    pub fn replica(&self, index: usize) -> Option<&PgPool> {
+        self.replicas.get(index).map(|replica| &replica.pool)
+    }
+
+    /// The current weight of the replica at `index`.
+    ///
+    /// Returns `None` if there is no replica at `index`.
+    pub fn replica_weight(&self, index: usize) -> Option<u32> {
+        self.replicas.get(index).map(ReplicaNode::weight)
+    }
+
+    /// The average read latency of the replica at `index`, as used by
+    /// [`LoadBalance::LeastLatency`].
+    ///
+    /// Returns `None` if there is no replica at `index` or no read on it has
+    /// been measured yet.
+    pub fn replica_latency(&self, index: usize) -> Option<Duration> {
+        self.replicas.get(index)?.latency.get()
+    }
+
+    /// Change the weight of the replica at `index`.
+    ///
+    /// Takes effect immediately for this `DbPools` and every clone of it.
+    /// A weight of `0` takes the replica out of rotation; if every replica
+    /// has a weight of `0`, reads go to the primary.
+    ///
+    /// Returns `false` if there is no replica at `index`.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # fn example(primary: PgPool, replicas: Vec<PgPool>) {
+    /// let pools = DbPools::with_replicas(primary, replicas);
+    ///
+    /// // Drain the first replica before maintenance
+    /// assert!(pools.set_replica_weight(0, 0));
+    /// # }
+    /// ```
+    pub fn set_replica_weight(&self, index: usize, weight: u32) -> bool {
+        match self.replicas.get(index) {
+            Some(replica) => {
+                replica.set_weight(weight);
+                true
+            }
+            None => false,
         }
     }
 
     /// Check if a replica pool is configured.
     ///
-    /// Returns `true` if a replica pool was provided via [`with_replica`](Self::with_replica).
+    /// Returns `true` if at least one replica pool was provided via
+    /// [`with_replica`](Self::with_replica) or [`with_replicas`](Self::with_replicas).
     ///
     /// # Example
     ///
@@ -331,12 +894,13 @@ impl DbPools {
     /// # }
     /// ```
     pub fn has_replica(&self) -> bool {
-        self.replica.is_some()
+        !self.replicas.is_empty()
     }
 
     /// Close all database connections.
     ///
-    /// Closes both primary and replica pools (if configured).
+    /// Closes the primary pool (every candidate, when built with
+    /// [`discover`](Self::discover)) and every replica pool.
     ///
     /// # Example
     ///
@@ -352,20 +916,31 @@ impl DbPools {
     /// # }
     /// ```
     pub async fn close(&self) {
-        self.primary.close().await;
-        if let Some(replica) = &self.replica {
+        for primary in self.primary_candidates() {
+            primary.close().await;
+        }
+        for replica in self.replicas() {
             replica.close().await;
         }
     }
 }
 
 impl PoolProvider for DbPools {
-    fn read(&self) -> &PgPool {
-        self.replica.as_ref().unwrap_or(&self.primary)
+    fn read(&self) -> &ReadPool {
+        if primary_reads_forced() {
+            return ReadPool::new(self.primary.current());
+        }
+        let Some(replica) = self.select_replica() else {
+            return ReadPool::new(self.primary.current());
+        };
+        if self.spills(&replica.pool) || !self.admits(replica) {
+            return ReadPool::new(self.primary.current());
+        }
+        ReadPool::new(&replica.pool)
     }
 
-    fn write(&self) -> &PgPool {
-        &self.primary
+    fn write(&self) -> &WritePool {
+        WritePool::new(self.primary.current())
     }
 }
 
@@ -377,7 +952,7 @@ impl Deref for DbPools {
     type Target = PgPool;
 
     fn deref(&self) -> &Self::Target {
-        &self.primary
+        self.primary.current()
     }
 }
 
@@ -408,12 +983,12 @@ impl Deref for DbPools {
 /// # }
 /// ```
 impl PoolProvider for PgPool {
-    fn read(&self) -> &PgPool {
-        self
+    fn read(&self) -> &ReadPool {
+        ReadPool::new(self)
     }
 
-    fn write(&self) -> &PgPool {
-        self
+    fn write(&self) -> &WritePool {
+        WritePool::new(self)
     }
 }
 
@@ -559,12 +1134,15 @@ impl TestDbPools {
 }
 
 impl PoolProvider for TestDbPools {
-    fn read(&self) -> &PgPool {
-        &self.replica
+    fn read(&self) -> &ReadPool {
+        if primary_reads_forced() {
+            return ReadPool::new(&self.primary);
+        }
+        ReadPool::new(&self.replica)
     }
 
-    fn write(&self) -> &PgPool {
-        &self.primary
+    fn write(&self) -> &WritePool {
+        WritePool::new(&self.primary)
     }
 }
 
@@ -573,6 +1151,34 @@ mod tests {
     use super::*;
     use sqlx::postgres::PgPoolOptions;
 
+    /// Pool handles that can be compared by the pool they wrap.
+    trait PoolRef {
+        fn pg_pool(&self) -> &PgPool;
+    }
+
+    impl PoolRef for PgPool {
+        fn pg_pool(&self) -> &PgPool {
+            self
+        }
+    }
+
+    impl PoolRef for ReadPool {
+        fn pg_pool(&self) -> &PgPool {
+            self.pool()
+        }
+    }
+
+    impl PoolRef for WritePool {
+        fn pg_pool(&self) -> &PgPool {
+            self.pool()
+        }
+    }
+
+    /// Whether two handles refer to the same pool.
+    fn same_pool(a: &impl PoolRef, b: &impl PoolRef) -> bool {
+        std::ptr::eq(a.pg_pool(), b.pg_pool())
+    }
+
     /// Helper to create a test database and return its pool and name
     async fn create_test_db(admin_pool: &PgPool, suffix: &str) -> (PgPool, String) {
         let db_name = format!("test_dbpools_{}", suffix);
@@ -723,6 +1329,1774 @@ mod tests {
         drop_test_db(&admin_pool, &replica_name).await;
     }
 
+    /// Helper to open an independent pool against the same database as `pool`
+    async fn separate_pool(pool: &PgPool) -> PgPool {
+        PgPoolOptions::new()
+            .max_connections(2)
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_dbpools_with_replicas_round_robin(pool: PgPool) {
+        let db_pools =
+            DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone(), pool.clone()]);
+        assert!(db_pools.has_replica());
+        assert_eq!(db_pools.replicas().len(), 3);
+
+        // Round-robin visits every replica in order, then wraps around
+        for round in 0..6 {
+            let expected = db_pools.replica(round % 3).unwrap();
+            assert!(
+                same_pool(db_pools.read(), expected),
+                "read #{} should use replica {}",
+                round,
+                round % 3
+            );
+        }
+
+        // Clones share the cursor, so they continue the rotation
+        let cloned = db_pools.clone();
+        assert!(same_pool(cloned.read(), db_pools.replica(0).unwrap()));
+        assert!(same_pool(db_pools.read(), db_pools.replica(1).unwrap()));
+
+        // Writes never touch a replica
+        assert!(same_pool(db_pools.write(), &*db_pools));
+    }
+
+    #[sqlx::test]
+    async fn test_dbpools_random_only_picks_replicas(pool: PgPool) {
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone()])
+            .load_balance(LoadBalance::Random);
+
+        for _ in 0..20 {
+            let read = db_pools.read();
+            assert!(db_pools.replicas().any(|r| same_pool(read, r)));
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_dbpools_least_busy_avoids_busy_replicas(pool: PgPool) {
+        let busy_a = separate_pool(&pool).await;
+        let busy_b = separate_pool(&pool).await;
+        let idle = separate_pool(&pool).await;
+
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![busy_a, busy_b, idle])
+            .load_balance(LoadBalance::LeastBusy);
+
+        // Hold connections on the first two replicas
+        let _conn_a = db_pools.replica(0).unwrap().acquire().await.unwrap();
+        let _conn_b = db_pools.replica(1).unwrap().acquire().await.unwrap();
+
+        for _ in 0..5 {
+            assert!(
+                same_pool(db_pools.read(), db_pools.replica(2).unwrap()),
+                "least-busy should pick the replica with no connections in use"
+            );
+        }
+
+        let result: (i32,) = sqlx::query_as("SELECT 1")
+            .fetch_one(db_pools.read())
+            .await
+            .unwrap();
+        assert_eq!(result.0, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_dbpools_weighted_round_robin(pool: PgPool) {
+        let db_pools = DbPools::with_replicas(
+            pool.clone(),
+            vec![
+                Replica::new(pool.clone()).weight(3),
+                Replica::new(pool.clone()).weight(1),
+            ],
+        );
+        assert_eq!(db_pools.replica_weight(0), Some(3));
+        assert_eq!(db_pools.replica_weight(1), Some(1));
+        assert_eq!(db_pools.replica_weight(2), None);
+
+        let count_reads = |pools: &DbPools, reads: usize| {
+            let mut counts = [0usize; 2];
+            for _ in 0..reads {
+                let read = pools.read();
+                let index = (0..2)
+                    .find(|&i| same_pool(read, pools.replica(i).unwrap()))
+                    .expect("read should use a replica");
+                counts[index] += 1;
+            }
+            counts
+        };
+
+        // Reads are spread 3:1
+        assert_eq!(count_reads(&db_pools, 40), [30, 10]);
+
+        // Weights can change at runtime, and clones see the change
+        let cloned = db_pools.clone();
+        assert!(cloned.set_replica_weight(1, 3));
+        assert_eq!(count_reads(&db_pools, 40), [20, 20]);
+
+        // A weight of zero drains the replica
+        assert!(db_pools.set_replica_weight(0, 0));
+        assert_eq!(count_reads(&db_pools, 10), [0, 10]);
+
+        // With every replica drained, reads fall back to the primary
+        assert!(db_pools.set_replica_weight(1, 0));
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        assert!(!db_pools.set_replica_weight(5, 1));
+    }
+
+    #[sqlx::test]
+    async fn test_dbpools_weighted_random(pool: PgPool) {
+        let db_pools = DbPools::with_replicas(
+            pool.clone(),
+            vec![
+                Replica::new(pool.clone()).weight(1),
+                Replica::new(pool.clone()).weight(0),
+            ],
+        )
+        .load_balance(LoadBalance::Random);
+
+        for _ in 0..50 {
+            assert!(same_pool(db_pools.read(), db_pools.replica(0).unwrap()));
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_health_check_removes_failed_replica(pool: PgPool) {
+        let failed = separate_pool(&pool).await;
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![failed, pool.clone()]);
+        let config = HealthCheckConfig::new().unhealthy_threshold(1);
+
+        // Replicas are healthy until probed
+        assert!(db_pools.replica_health().iter().all(|h| h.healthy));
+
+        db_pools.replica(0).unwrap().close().await;
+        db_pools.check_replica_health(&config).await;
+
+        let health = db_pools.replica_health();
+        assert!(!health[0].healthy);
+        assert_eq!(health[0].consecutive_failures, 1);
+        assert!(health[0].last_error.is_some());
+        assert!(health[0].last_checked.is_some());
+        assert!(health[1].healthy);
+        assert_eq!(health[1].last_error, None);
+
+        // Reads skip the failed replica
+        for _ in 0..5 {
+            assert!(same_pool(db_pools.read(), db_pools.replica(1).unwrap()));
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_health_check_falls_back_to_primary_and_recovers(pool: PgPool) {
+        let db_pools = DbPools::with_replica(pool.clone(), pool.clone());
+        let config = HealthCheckConfig::new()
+            .unhealthy_threshold(2)
+            .healthy_threshold(2);
+        let health = &db_pools.replicas[0].health;
+
+        // A single failure stays below the threshold
+        health.record_failure(&config, "connection refused".to_string());
+        assert!(db_pools.replica_health()[0].healthy);
+
+        health.record_failure(&config, "connection refused".to_string());
+        assert!(!db_pools.replica_health()[0].healthy);
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        // Recovery also needs consecutive successes
+        db_pools.check_replica_health(&config).await;
+        assert!(!db_pools.replica_health()[0].healthy);
+        db_pools.check_replica_health(&config).await;
+        assert!(db_pools.replica_health()[0].healthy);
+        assert!(same_pool(db_pools.read(), db_pools.replica(0).unwrap()));
+    }
+
+    #[sqlx::test]
+    async fn test_spawned_health_checker_stops_with_pools(pool: PgPool) {
+        let failed = separate_pool(&pool).await;
+        let db_pools = DbPools::with_replica(pool.clone(), failed);
+        db_pools.replica(0).unwrap().close().await;
+
+        let checker = db_pools.spawn_health_checker(
+            HealthCheckConfig::new()
+                .interval(std::time::Duration::from_millis(10))
+                .unhealthy_threshold(1),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        assert!(!db_pools.replica_health()[0].healthy);
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        // Dropping the last DbPools ends the background task
+        drop(db_pools);
+        tokio::time::timeout(std::time::Duration::from_secs(1), checker)
+            .await
+            .expect("health checker should stop once the pools are dropped")
+            .unwrap();
+    }
+
+    #[sqlx::test]
+    async fn test_lag_sampling_on_servers_not_in_recovery(pool: PgPool) {
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone()]);
+        let config = ReplicationLagConfig::new(std::time::Duration::ZERO).max_lag_bytes(0);
+
+        db_pools.sample_replication_lag(&config).await;
+
+        // A server that isn't replaying WAL is never behind
+        for lag in db_pools.replica_lag() {
+            assert_eq!(lag.lag, Some(std::time::Duration::ZERO));
+            assert_eq!(lag.lag_bytes, Some(0));
+            assert!(lag.within_limit);
+            assert!(lag.sampled_at.is_some());
+            assert_eq!(lag.last_error, None);
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_lagging_replicas_are_skipped(pool: PgPool) {
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone()]);
+        let config = ReplicationLagConfig::new(std::time::Duration::from_secs(1));
+
+        db_pools.replicas[0]
+            .lag
+            .record(&config, std::time::Duration::from_secs(30), None);
+        assert!(!db_pools.replica_lag()[0].within_limit);
+        for _ in 0..5 {
+            assert!(same_pool(db_pools.read(), db_pools.replica(1).unwrap()));
+        }
+
+        // With every replica behind, reads fall back to the primary
+        db_pools.replicas[1]
+            .lag
+            .record(&config, std::time::Duration::from_secs(5), None);
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        // Catching up puts the replica back in rotation
+        db_pools.sample_replication_lag(&config).await;
+        assert!(db_pools.replica_lag().iter().all(|lag| lag.within_limit));
+    }
+
+    #[sqlx::test]
+    async fn test_unsampleable_replica_is_treated_as_lagging(pool: PgPool) {
+        let failed = separate_pool(&pool).await;
+        let db_pools = DbPools::with_replica(pool.clone(), failed);
+        db_pools.replica(0).unwrap().close().await;
+
+        db_pools
+            .sample_replication_lag(&ReplicationLagConfig::new(std::time::Duration::from_secs(
+                1,
+            )))
+            .await;
+
+        let lag = &db_pools.replica_lag()[0];
+        assert!(!lag.within_limit);
+        assert_eq!(lag.lag, None);
+        assert!(lag.last_error.is_some());
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+    }
+
+    #[test]
+    fn test_lsn_parse_and_display() {
+        let lsn: Lsn = "16/B374D848".parse().unwrap();
+        assert_eq!(lsn.as_u64(), (0x16 << 32) | 0xB374D848);
+        assert_eq!(lsn.to_string(), "16/B374D848");
+        assert_eq!(Lsn::from_u64(0).to_string(), "0/0");
+        assert!(lsn < "17/0".parse().unwrap());
+
+        assert!("".parse::<Lsn>().is_err());
+        assert!("16B374D848".parse::<Lsn>().is_err());
+        assert!("16/XYZ".parse::<Lsn>().is_err());
+        assert!("1FFFFFFFF/0".parse::<Lsn>().is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_read_after_lsn_uses_caught_up_replica(pool: PgPool) {
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone()]);
+
+        sqlx::query("CREATE TABLE ryw (id INT)")
+            .execute(db_pools.write())
+            .await
+            .unwrap();
+        let lsn = db_pools.current_wal_lsn().await.unwrap();
+        assert!(lsn > Lsn::default());
+
+        // The "replicas" here are the primary itself, so they've always caught up
+        let read = db_pools
+            .read_after_lsn(lsn, std::time::Duration::from_secs(1))
+            .await;
+        assert!(db_pools.replicas().any(|r| same_pool(read, r)));
+    }
+
+    #[sqlx::test]
+    async fn test_read_after_lsn_polls_every_replica(pool: PgPool) {
+        /// Records the replicas offered on each call, and picks the first.
+        #[derive(Debug, Default)]
+        struct Recording {
+            calls: std::sync::Mutex<Vec<Vec<usize>>>,
+        }
+
+        impl RoutingPolicy for std::sync::Arc<Recording> {
+            fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+                self.calls
+                    .lock()
+                    .unwrap()
+                    .push(candidates.iter().map(Candidate::index).collect());
+                Some(0)
+            }
+        }
+
+        // A closed pool never reports a replay position
+        let behind = separate_pool(&pool).await;
+        behind.close().await;
+        let policy = std::sync::Arc::new(Recording::default());
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![behind, pool.clone()])
+            .routing_policy(policy.clone());
+        let lsn = db_pools.current_wal_lsn().await.unwrap();
+
+        // The caught-up replica is found even though it isn't the first, and
+        // the policy only chooses among caught-up replicas
+        let read = db_pools
+            .read_after_lsn(lsn, std::time::Duration::from_secs(1))
+            .await;
+        assert!(same_pool(read, db_pools.replica(1).unwrap()));
+        assert_eq!(*policy.calls.lock().unwrap(), vec![vec![1]]);
+
+        // Polling while no replica has caught up doesn't consult the policy
+        let read = db_pools
+            .read_after_lsn(
+                Lsn::from_u64(u64::MAX),
+                std::time::Duration::from_millis(50),
+            )
+            .await;
+        assert!(same_pool(read, db_pools.write()));
+        assert_eq!(policy.calls.lock().unwrap().len(), 1);
+    }
+
+    #[sqlx::test]
+    async fn test_read_after_lsn_falls_back_to_primary(pool: PgPool) {
+        let db_pools = DbPools::with_replica(pool.clone(), pool.clone());
+        let unreachable = Lsn::from_u64(u64::MAX);
+
+        // Without waiting, the primary is used straight away
+        let read = db_pools
+            .read_after_lsn(unreachable, std::time::Duration::ZERO)
+            .await;
+        assert!(same_pool(read, db_pools.write()));
+
+        // Otherwise replicas are polled until the timeout elapses
+        let started = std::time::Instant::now();
+        let read = db_pools
+            .read_after_lsn(unreachable, std::time::Duration::from_millis(50))
+            .await;
+        assert!(same_pool(read, db_pools.write()));
+        assert!(started.elapsed() >= std::time::Duration::from_millis(50));
+
+        // A replica that doesn't answer can't hold the read past the deadline
+        let hung = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap();
+        let _held = hung.acquire().await.unwrap();
+        let db_pools = DbPools::with_replica(pool.clone(), hung);
+        let started = std::time::Instant::now();
+        let read = db_pools
+            .read_after_lsn(Lsn::default(), std::time::Duration::from_millis(100))
+            .await;
+        assert!(same_pool(read, db_pools.write()));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+
+        // Without any replica the primary is used
+        let single = DbPools::new(pool);
+        let read = single
+            .read_after_lsn(Lsn::default(), std::time::Duration::from_millis(50))
+            .await;
+        assert!(same_pool(read, single.write()));
+    }
+
+    #[test]
+    fn test_consistency_token_encoding() {
+        let token = ConsistencyToken::from("16/B374D848".parse::<Lsn>().unwrap());
+        assert_eq!(token.to_string(), "v1.00000016B374D848");
+        assert_eq!("v1.00000016B374D848".parse(), Ok(token));
+        assert_eq!("v1.00000016b374d848".parse(), Ok(token));
+        assert_eq!(
+            ConsistencyToken::default().to_string(),
+            "v1.0000000000000000"
+        );
+
+        assert!("00000016B374D848".parse::<ConsistencyToken>().is_err());
+        assert!("v2.00000016B374D848".parse::<ConsistencyToken>().is_err());
+        assert!("v1.16B374D848".parse::<ConsistencyToken>().is_err());
+        assert!("v1.+0000016B374D848".parse::<ConsistencyToken>().is_err());
+
+        let later = ConsistencyToken::from(Lsn::from_u64(token.lsn().as_u64() + 1));
+        assert_eq!(token.merge(later), later);
+        assert_eq!(later.merge(token), later);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_consistency_token_serde() {
+        let token = ConsistencyToken::from(Lsn::from_u64(0x16_B374_D848));
+        let json = serde_json::to_string(&token).unwrap();
+        assert_eq!(json, "\"v1.00000016B374D848\"");
+        assert_eq!(
+            serde_json::from_str::<ConsistencyToken>(&json).unwrap(),
+            token
+        );
+        assert!(serde_json::from_str::<ConsistencyToken>("\"16/B374D848\"").is_err());
+    }
+
+    #[sqlx::test]
+    async fn test_read_consistent_with_propagated_token(pool: PgPool) {
+        let service_a = DbPools::new(pool.clone());
+        let service_b = DbPools::with_replica(pool.clone(), pool.clone());
+
+        sqlx::query("CREATE TABLE orders (id INT)")
+            .execute(service_a.write())
+            .await
+            .unwrap();
+        let header = service_a.consistency_token().await.unwrap().to_string();
+
+        let token: ConsistencyToken = header.parse().unwrap();
+        let read = service_b
+            .read_consistent(token, std::time::Duration::from_secs(1))
+            .await;
+        assert!(same_pool(read, service_b.replica(0).unwrap()));
+
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM orders")
+            .fetch_one(read)
+            .await
+            .unwrap();
+        assert_eq!(count, 0);
+    }
+
+    #[sqlx::test]
+    async fn test_session_reads_primary_after_write(pool: PgPool) {
+        let db_pools = DbPools::with_replica(pool.clone(), pool.clone());
+        let session = db_pools.session(std::time::Duration::from_millis(50));
+        let replica = db_pools.replica(0).unwrap();
+        let primary = session.pools().write();
+
+        // Before any write, reads use the replica
+        assert!(!session.is_sticky());
+        assert_eq!(session.last_write(), None);
+        assert!(same_pool(session.read(), replica));
+
+        // A write pins reads to the primary, for every clone of the session
+        assert!(same_pool(session.write(), primary));
+        let cloned = session.clone();
+        assert!(cloned.is_sticky());
+        assert!(same_pool(cloned.read(), cloned.pools().write()));
+
+        // Other sessions are unaffected
+        let other = db_pools.session(std::time::Duration::from_millis(50));
+        assert!(same_pool(other.read(), replica));
+
+        // Once the window passes, reads go back to the replica
+        tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+        assert!(!session.is_sticky());
+        assert!(same_pool(session.read(), replica));
+
+        // Writes made elsewhere can still start a window
+        session.mark_write();
+        assert!(same_pool(session.read(), primary));
+    }
+
+    #[sqlx::test]
+    async fn test_with_primary_reads_scope(pool: PgPool) {
+        let db_pools = DbPools::with_replica(pool.clone(), pool.clone());
+        let replica = db_pools.replica(0).unwrap();
+        let primary = db_pools.write();
+
+        assert!(!primary_reads_forced());
+        assert!(same_pool(db_pools.read(), replica));
+
+        with_primary_reads(async {
+            assert!(primary_reads_forced());
+            assert!(same_pool(db_pools.read(), primary));
+
+            // Sessions and LSN-based routing honor the scope too
+            let session = db_pools.session(std::time::Duration::ZERO);
+            assert!(same_pool(session.read(), session.pools().write()));
+            let read = db_pools
+                .read_after_lsn(Lsn::default(), std::time::Duration::ZERO)
+                .await;
+            assert!(same_pool(read, primary));
+
+            // The scope survives across awaits
+            tokio::task::yield_now().await;
+            assert!(same_pool(db_pools.read(), primary));
+        })
+        .await;
+
+        // Outside the scope, routing is back to normal
+        assert!(same_pool(db_pools.read(), replica));
+    }
+
+    #[sqlx::test]
+    async fn test_with_primary_reads_on_testdbpools(pool: PgPool) {
+        let pools = TestDbPools::new(pool).await.unwrap();
+
+        let result = with_primary_reads(async {
+            sqlx::query("CREATE TEMP TABLE forced_primary (id INT)")
+                .execute(pools.read())
+                .await
+        })
+        .await;
+        assert!(result.is_ok(), "forced reads should use the writable pool");
+    }
+
+    /// Helper to open a pool whose connections default to read-only transactions
+    async fn read_only_pool(pool: &PgPool) -> PgPool {
+        PgPoolOptions::new()
+            .max_connections(2)
+            .after_connect(|conn, _meta| {
+                Box::pin(async move {
+                    sqlx::query("SET default_transaction_read_only = on")
+                        .execute(&mut *conn)
+                        .await?;
+                    Ok(())
+                })
+            })
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap()
+    }
+
+    #[sqlx::test]
+    async fn test_discover_picks_writable_candidate(pool: PgPool) {
+        let standby = read_only_pool(&pool).await;
+        let db_pools = DbPools::discover(vec![standby, pool.clone()], vec![pool.clone()])
+            .await
+            .unwrap();
+
+        assert_eq!(db_pools.primary_candidates().len(), 2);
+        assert_eq!(db_pools.primary_index(), 1);
+        let candidate = db_pools.primary_candidates().nth(1).unwrap();
+        assert!(same_pool(db_pools.write(), candidate));
+        assert!(same_pool(&*db_pools, candidate));
+
+        sqlx::query("CREATE TABLE discovered (id INT)")
+            .execute(db_pools.write())
+            .await
+            .unwrap();
+
+        // Still writable, so refreshing keeps the same primary
+        assert!(!db_pools.refresh_primary().await.unwrap());
+        assert_eq!(db_pools.primary_index(), 1);
+    }
+
+    #[sqlx::test]
+    async fn test_discover_fails_without_writable_candidate(pool: PgPool) {
+        let standby_a = read_only_pool(&pool).await;
+        let standby_b = read_only_pool(&pool).await;
+
+        let result = DbPools::discover(vec![standby_a, standby_b], Vec::<PgPool>::new()).await;
+        assert!(matches!(result, Err(sqlx::Error::Configuration(_))));
+    }
+
+    #[sqlx::test]
+    async fn test_write_with_failover_follows_new_primary(pool: PgPool) {
+        // A single connection, so demoting it demotes the whole pool
+        let old_primary = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap();
+        let new_primary = separate_pool(&pool).await;
+        let db_pools = DbPools::discover(vec![old_primary, new_primary], Vec::<PgPool>::new())
+            .await
+            .unwrap();
+        assert_eq!(db_pools.primary_index(), 0);
+
+        sqlx::query("CREATE TABLE failover_events (id INT)")
+            .execute(db_pools.write())
+            .await
+            .unwrap();
+
+        // Simulate the old primary being demoted to a read-only standby
+        sqlx::query("SET default_transaction_read_only = on")
+            .execute(db_pools.write())
+            .await
+            .unwrap();
+
+        let mut attempts = 0;
+        db_pools
+            .write_with_failover(async |pool| {
+                attempts += 1;
+                let result = sqlx::query("INSERT INTO failover_events VALUES (1)")
+                    .execute(pool)
+                    .await;
+                if attempts == 1 {
+                    let err = result.as_ref().unwrap_err();
+                    assert!(is_failover_error(err), "unexpected error: {}", err);
+                }
+                result
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(attempts, 2);
+        assert_eq!(db_pools.primary_index(), 1);
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM failover_events")
+            .fetch_one(db_pools.write())
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // Errors unrelated to failover are returned without a retry
+        let mut attempts = 0;
+        let result = db_pools
+            .write_with_failover(async |pool| {
+                attempts += 1;
+                sqlx::query("INSERT INTO missing_table VALUES (1)")
+                    .execute(pool)
+                    .await
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_validate_topology(pool: PgPool) {
+        // A single pool has nothing to disagree with
+        DbPools::new(pool.clone()).validate().await.unwrap();
+
+        // Replicas are expected to be in recovery; the test server isn't
+        let db_pools = DbPools::with_replicas(
+            pool.clone(),
+            vec![
+                Replica::new(pool.clone()).skip_recovery_check(),
+                Replica::new(pool.clone()),
+            ],
+        );
+        let err = db_pools.validate().await.unwrap_err();
+        assert!(
+            matches!(err, TopologyError::ReplicaNotInRecovery { replica: 1 }),
+            "unexpected error: {}",
+            err
+        );
+
+        let db_pools = DbPools::with_replicas(
+            pool.clone(),
+            vec![Replica::new(pool.clone()).skip_recovery_check()],
+        );
+        db_pools.validate().await.unwrap();
+    }
+
+    #[test]
+    fn test_validate_skipped_replicas_may_be_another_cluster() {
+        use topology::{check_replica, Identity};
+
+        let primary = Identity {
+            in_recovery: false,
+            system_identifier: 1,
+            database: "app".to_string(),
+        };
+        // A logical replication subscriber: writable, with its own identifier
+        let subscriber = || Identity {
+            in_recovery: false,
+            system_identifier: 2,
+            database: "app".to_string(),
+        };
+        check_replica(0, false, &primary, subscriber()).unwrap();
+        assert!(matches!(
+            check_replica(0, true, &primary, subscriber()),
+            Err(TopologyError::ReplicaNotInRecovery { replica: 0 })
+        ));
+
+        // Physical replicas must share the primary's cluster
+        let standby = Identity {
+            in_recovery: true,
+            ..subscriber()
+        };
+        assert!(matches!(
+            check_replica(1, true, &primary, standby),
+            Err(TopologyError::SystemIdentifierMismatch {
+                replica: 1,
+                primary: 1,
+                found: 2
+            })
+        ));
+
+        // The database name is checked either way
+        let other_database = Identity {
+            database: "other".to_string(),
+            ..subscriber()
+        };
+        assert!(matches!(
+            check_replica(0, false, &primary, other_database),
+            Err(TopologyError::DatabaseMismatch { replica: 0, .. })
+        ));
+    }
+
+    #[sqlx::test]
+    async fn test_validate_detects_database_mismatch(pool: PgPool) {
+        let admin_pool = PgPoolOptions::new()
+            .max_connections(2)
+            .connect(&build_test_url("postgres"))
+            .await
+            .unwrap();
+        let (other_pool, other_name) = create_test_db(&admin_pool, "validate_other").await;
+
+        let db_pools = DbPools::with_replicas(
+            pool.clone(),
+            vec![Replica::new(other_pool.clone()).skip_recovery_check()],
+        );
+        let err = db_pools.validate().await.unwrap_err();
+        match &err {
+            TopologyError::DatabaseMismatch {
+                replica,
+                primary,
+                found,
+            } => {
+                assert_eq!(*replica, 0);
+                assert_eq!(found, &other_name);
+                assert_ne!(primary, &other_name);
+            }
+            other => panic!("unexpected error: {}", other),
+        }
+
+        // Unreachable pools are reported with their role
+        other_pool.close().await;
+        let err = db_pools.validate().await.unwrap_err();
+        assert!(matches!(
+            err,
+            TopologyError::Query {
+                pool: PoolRole::Replica(0),
+                ..
+            }
+        ));
+        assert!(std::error::Error::source(&err).is_some());
+
+        drop_test_db(&admin_pool, &other_name).await;
+    }
+
+    #[test]
+    fn test_classify_statements() {
+        use StatementKind::{Read, Write};
+
+        let cases = [
+            ("SELECT 1", Read),
+            ("  select * from users where id = $1", Read),
+            (
+                "WITH recent AS (SELECT * FROM orders) SELECT count(*) FROM recent",
+                Read,
+            ),
+            ("SHOW server_version", Read),
+            ("VALUES (1), (2)", Read),
+            ("-- comment\n/* nested /* comment */ */ SELECT 1", Read),
+            ("SELECT 'DELETE FROM users', \"update\" FROM t", Read),
+            ("SELECT E'it\\'s UPDATE', $tag$ INSERT $tag$", Read),
+            ("SELECT $$ nextval('s') $$", Read),
+            ("SELECT format  FROM t -- FOR UPDATE", Read),
+            ("INSERT INTO users (name) VALUES ('Alice')", Write),
+            ("update users set name = 'Bob'", Write),
+            ("DELETE FROM users", Write),
+            ("CREATE TABLE t (id INT)", Write),
+            ("TRUNCATE t", Write),
+            ("SELECT * FROM jobs FOR UPDATE SKIP LOCKED", Write),
+            ("SELECT * FROM jobs FOR NO KEY UPDATE", Write),
+            ("SELECT * FROM jobs FOR SHARE", Write),
+            ("SELECT nextval('users_id_seq')", Write),
+            ("SELECT pg_notify('jobs', 'ready')", Write),
+            ("SELECT pg_try_advisory_lock(42)", Write),
+            ("SELECT pg_advisory_unlock(42)", Write),
+            ("SELECT pg_advisory_unlock_shared(42)", Write),
+            ("SELECT pg_advisory_unlock_all()", Write),
+            ("SELECT * INTO backup FROM users", Write),
+            (
+                "WITH gone AS (DELETE FROM t RETURNING id) SELECT * FROM gone",
+                Write,
+            ),
+            ("WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x", Write),
+            ("SELECT 1; DELETE FROM t", Write),
+            ("BEGIN", Write),
+            ("", Write),
+        ];
+        for (sql, expected) in cases {
+            assert_eq!(classify(sql), expected, "{}", sql);
+        }
+    }
+
+    #[cfg(feature = "sql-parser")]
+    #[test]
+    fn test_classify_with_parser() {
+        use StatementKind::{Read, Write};
+
+        // Keywords used as identifiers would trip up a keyword scan
+        assert_eq!(classify("SELECT update, delete FROM audit_log"), Read);
+        assert_eq!(classify("SELECT into_count, share FROM t"), Read);
+
+        assert_eq!(classify("SELECT * FROM pg_catalog.nextval('s')"), Write);
+        assert_eq!(
+            classify("SELECT id FROM t WHERE pg_try_advisory_xact_lock(id)"),
+            Write
+        );
+        assert_eq!(
+            classify("WITH x AS (UPDATE t SET a = 1 RETURNING id) SELECT * FROM x"),
+            Write
+        );
+        assert_eq!(
+            classify("SELECT id FROM t WHERE x IN (SELECT y FROM u FOR SHARE)"),
+            Write
+        );
+        assert_eq!(classify("EXPLAIN ANALYZE DELETE FROM t"), Write);
+        // Statements the parser can't handle fall back to the scan, such as
+        // DELETE in a writable CTE, which sqlparser 0.53 rejects
+        for sql in [
+            "WITH gone AS (DELETE FROM t RETURNING id) SELECT * FROM gone",
+            "WITH x AS (DELETE FROM t RETURNING *) SELECT * FROM x",
+        ] {
+            assert_eq!(classify::parser::classify(sql), None, "{}", sql);
+            assert_eq!(classify(sql), Write, "{}", sql);
+        }
+    }
+
+    #[sqlx::test]
+    async fn test_auto_router_routes_by_statement(pool: PgPool) {
+        let pools = TestDbPools::new(pool).await.unwrap();
+        let auto = pools.auto();
+
+        assert!(same_pool(auto.route("SELECT 1"), pools.read()));
+        assert!(same_pool(auto.route("SELECT 1 FOR UPDATE"), pools.write()));
+
+        // Writes succeed, so they can't have gone to the read-only pool
+        sqlx::query("CREATE TABLE auto_routed (id SERIAL PRIMARY KEY, name TEXT)")
+            .execute(auto)
+            .await
+            .unwrap();
+        let id: i32 = sqlx::query_scalar("INSERT INTO auto_routed (name) VALUES ($1) RETURNING id")
+            .bind("Alice")
+            .fetch_one(auto)
+            .await
+            .unwrap();
+
+        let read_only: String =
+            sqlx::query_scalar("SELECT current_setting('default_transaction_read_only')")
+                .fetch_one(auto)
+                .await
+                .unwrap();
+        assert_eq!(read_only, "on");
+        let name: Option<String> = sqlx::query_scalar("SELECT name FROM auto_routed WHERE id = $1")
+            .bind(id)
+            .fetch_optional(auto)
+            .await
+            .unwrap();
+        assert_eq!(name.as_deref(), Some("Alice"));
+
+        let locked: Option<i32> =
+            sqlx::query_scalar("SELECT id FROM auto_routed WHERE id = $1 FOR UPDATE")
+                .bind(id)
+                .fetch_optional(auto)
+                .await
+                .unwrap();
+        assert_eq!(locked, Some(id));
+
+        // Forced primary reads still apply
+        let read_only: String = with_primary_reads(async {
+            sqlx::query_scalar("SELECT current_setting('default_transaction_read_only')")
+                .fetch_one(pools.auto())
+                .await
+        })
+        .await
+        .unwrap();
+        assert_eq!(read_only, "off");
+    }
+
+    #[sqlx::test]
+    async fn test_read_and_write_pool_types(pool: PgPool) {
+        async fn count_items(db: &ReadPool) -> i64 {
+            sqlx::query_scalar("SELECT COUNT(*) FROM typed_items")
+                .fetch_one(db)
+                .await
+                .unwrap()
+        }
+
+        let pools = TestDbPools::new(pool).await.unwrap();
+        sqlx::query("CREATE TABLE typed_items (id INT)")
+            .execute(pools.write())
+            .await
+            .unwrap();
+
+        let mut tx = pools.write().begin().await.unwrap();
+        sqlx::query("INSERT INTO typed_items VALUES (1)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let mut conn = pools.write().acquire().await.unwrap();
+        sqlx::query("INSERT INTO typed_items VALUES (2)")
+            .execute(&mut *conn)
+            .await
+            .unwrap();
+        drop(conn);
+
+        // A write pool can be used wherever a read pool is expected
+        assert_eq!(count_items(pools.read()).await, 2);
+        assert_eq!(count_items(pools.write()).await, 2);
+        assert!(same_pool(&**pools.write(), pools.write()));
+    }
+
+    #[sqlx::test]
+    async fn test_begin_read_and_write(pool: PgPool) {
+        async fn setting(tx: &mut Transaction<'static, Postgres>, name: &str) -> String {
+            sqlx::query_scalar("SELECT current_setting($1)")
+                .bind(name)
+                .fetch_one(&mut **tx)
+                .await
+                .unwrap()
+        }
+
+        let pools = TestDbPools::new(pool).await.unwrap();
+
+        let mut tx = pools.begin_write().await.unwrap();
+        assert_eq!(
+            setting(&mut tx, "default_transaction_read_only").await,
+            "off"
+        );
+        sqlx::query("CREATE TABLE tx_items (id INT)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+
+        let options = TransactionOptions::new().isolation(IsolationLevel::RepeatableRead);
+        let mut tx = pools.begin_write_with(options).await.unwrap();
+        assert_eq!(
+            setting(&mut tx, "transaction_isolation").await,
+            "repeatable read"
+        );
+        tx.rollback().await.unwrap();
+
+        // Read transactions go to the read pool and are read-only
+        let mut tx = pools.begin_read().await.unwrap();
+        assert_eq!(
+            setting(&mut tx, "default_transaction_read_only").await,
+            "on"
+        );
+        assert_eq!(setting(&mut tx, "transaction_read_only").await, "on");
+        tx.rollback().await.unwrap();
+
+        let options = TransactionOptions::new().isolation(IsolationLevel::RepeatableRead);
+        let mut tx = pools.begin_read_with(options).await.unwrap();
+        assert_eq!(
+            setting(&mut tx, "default_transaction_read_only").await,
+            "on"
+        );
+        assert_eq!(
+            setting(&mut tx, "transaction_isolation").await,
+            "repeatable read"
+        );
+        tx.rollback().await.unwrap();
+
+        // Serializable reads run on the primary, still read-only
+        let options = TransactionOptions::new()
+            .isolation(IsolationLevel::Serializable)
+            .deferrable();
+        let mut tx = pools.begin_read_with(options).await.unwrap();
+        assert_eq!(
+            setting(&mut tx, "default_transaction_read_only").await,
+            "off"
+        );
+        assert_eq!(setting(&mut tx, "transaction_read_only").await, "on");
+        assert_eq!(
+            setting(&mut tx, "transaction_isolation").await,
+            "serializable"
+        );
+        assert_eq!(setting(&mut tx, "transaction_deferrable").await, "on");
+        let result = sqlx::query("INSERT INTO tx_items VALUES (1)")
+            .execute(&mut *tx)
+            .await;
+        assert!(result.is_err(), "read transactions should reject writes");
+    }
+
+    #[sqlx::test]
+    async fn test_write_tx_commits_and_rolls_back(pool: PgPool) {
+        let db_pools = DbPools::new(pool);
+        sqlx::query("CREATE TABLE write_tx_items (id INT)")
+            .execute(db_pools.write())
+            .await
+            .unwrap();
+        let count = async || -> i64 {
+            sqlx::query_scalar("SELECT COUNT(*) FROM write_tx_items")
+                .fetch_one(db_pools.read())
+                .await
+                .unwrap()
+        };
+
+        let inserted = db_pools
+            .write_tx(async |tx| {
+                sqlx::query("INSERT INTO write_tx_items VALUES (1)")
+                    .execute(&mut **tx)
+                    .await
+            })
+            .await
+            .unwrap();
+        assert_eq!(inserted.rows_affected(), 1);
+        assert_eq!(count().await, 1);
+
+        // Errors roll back and aren't retried
+        let mut attempts = 0;
+        let result: Result<(), _> = db_pools
+            .write_tx(async |tx| {
+                attempts += 1;
+                sqlx::query("INSERT INTO write_tx_items VALUES (2)")
+                    .execute(&mut **tx)
+                    .await?;
+                Err(sqlx::Error::RowNotFound)
+            })
+            .await;
+        assert!(matches!(result, Err(sqlx::Error::RowNotFound)));
+        assert_eq!(attempts, 1);
+        assert_eq!(count().await, 1);
+    }
+
+    #[sqlx::test]
+    async fn test_write_tx_retries_conflicts(pool: PgPool) {
+        let db_pools = DbPools::new(pool);
+        sqlx::query("CREATE TABLE write_tx_retried (id INT)")
+            .execute(db_pools.write())
+            .await
+            .unwrap();
+        let raise = |code: &str| {
+            format!(
+                "DO $$ BEGIN RAISE EXCEPTION 'conflict' USING ERRCODE = '{}'; END $$",
+                code
+            )
+        };
+        let config = WriteTxConfig::new()
+            .isolation(IsolationLevel::Serializable)
+            .max_attempts(3)
+            .backoff(std::time::Duration::from_millis(1));
+
+        // A serialization failure then a deadlock, then success
+        let mut attempts = 0;
+        db_pools
+            .write_tx_with(&config, async |tx| {
+                attempts += 1;
+                sqlx::query("INSERT INTO write_tx_retried VALUES (1)")
+                    .execute(&mut **tx)
+                    .await?;
+                let isolation: String =
+                    sqlx::query_scalar("SELECT current_setting('transaction_isolation')")
+                        .fetch_one(&mut **tx)
+                        .await?;
+                assert_eq!(isolation, "serializable");
+                match attempts {
+                    1 => sqlx::query(&raise("40001")).execute(&mut **tx).await?,
+                    2 => sqlx::query(&raise("40P01")).execute(&mut **tx).await?,
+                    _ => Default::default(),
+                };
+                Ok(())
+            })
+            .await
+            .unwrap();
+        assert_eq!(attempts, 3);
+        let rows: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM write_tx_retried")
+            .fetch_one(db_pools.read())
+            .await
+            .unwrap();
+        assert_eq!(rows, 1, "failed attempts should be rolled back");
+
+        // Gives up after max_attempts
+        let mut attempts = 0;
+        let err = db_pools
+            .write_tx_with(&config, async |tx| {
+                attempts += 1;
+                sqlx::query(&raise("40001")).execute(&mut **tx).await
+            })
+            .await
+            .unwrap_err();
+        assert!(is_transaction_conflict(&err));
+        assert_eq!(attempts, 3);
+    }
+
+    #[sqlx::test]
+    async fn test_retry_read_on_recovery_conflict(pool: PgPool) {
+        const CONFLICT: &str = "DO $$ BEGIN RAISE EXCEPTION \
+            'canceling statement due to conflict with recovery' USING ERRCODE = '40001'; END $$";
+
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone()]);
+        let which = |read: &ReadPool| {
+            (0..2)
+                .find(|&i| same_pool(read, db_pools.replica(i).unwrap()))
+                .map_or("primary".to_string(), |i| format!("replica {}", i))
+        };
+
+        // Replicas keep cancelling: try the other replica, then the primary
+        let mut visited = Vec::new();
+        let value: i32 = db_pools
+            .retry_read(async |read| {
+                visited.push(which(read));
+                if visited.len() < 3 {
+                    sqlx::query(CONFLICT).execute(read).await?;
+                }
+                sqlx::query_scalar("SELECT 1").fetch_one(read).await
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, 1);
+        assert_eq!(visited, ["replica 0", "replica 1", "primary"]);
+        assert_eq!(db_pools.read_retries(), 2);
+
+        // Straight to the primary after the first conflict
+        let config = ReadRetryConfig::new().primary_after(1);
+        let mut visited = Vec::new();
+        let err = db_pools
+            .retry_read_with(&config, async |read| {
+                visited.push(which(read));
+                sqlx::query(CONFLICT).execute(read).await
+            })
+            .await
+            .unwrap_err();
+        assert!(is_recovery_conflict(&err));
+        assert_eq!(visited, ["replica 1", "primary", "primary"]);
+        assert_eq!(db_pools.read_retries(), 4);
+        assert_eq!(db_pools.clone().read_retries(), 4);
+
+        // Other errors aren't retried
+        let mut attempts = 0;
+        let result = db_pools
+            .retry_read(async |read| {
+                attempts += 1;
+                sqlx::query("SELECT * FROM missing_table")
+                    .execute(read)
+                    .await
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert_eq!(db_pools.read_retries(), 4);
+
+        // A conflict reported in another language is still recognized on a
+        // replica, from its SQLSTATE
+        let translated = "DO $$ BEGIN RAISE EXCEPTION \
+            'Abbruch der Anweisung wegen Konflikt mit der Wiederherstellung' \
+            USING ERRCODE = '40001'; END $$";
+        let mut visited = Vec::new();
+        let err = db_pools
+            .retry_read(async |read| {
+                visited.push(which(read));
+                sqlx::query(translated).execute(read).await
+            })
+            .await
+            .unwrap_err();
+        assert!(!is_recovery_conflict(&err));
+        assert_eq!(visited, ["replica 1", "replica 0", "primary"]);
+        assert_eq!(db_pools.read_retries(), 6);
+
+        // But on the primary, serialization failures aren't retried
+        let mut attempts = 0;
+        let err = with_primary_reads(db_pools.retry_read(async |read| {
+            attempts += 1;
+            sqlx::query(
+                "DO $$ BEGIN RAISE EXCEPTION 'could not serialize access' \
+                USING ERRCODE = '40001'; END $$",
+            )
+            .execute(read)
+            .await
+        }))
+        .await
+        .unwrap_err();
+        assert!(!is_recovery_conflict(&err));
+        assert!(is_transaction_conflict(&err));
+        assert_eq!(attempts, 1);
+        assert_eq!(db_pools.read_retries(), 6);
+    }
+
+    #[sqlx::test]
+    async fn test_read_with_fallback_on_connection_errors(pool: PgPool) {
+        // A replica whose only connection is taken times out on acquire
+        let replica = PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_millis(50))
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap();
+        let _held = replica.acquire().await.unwrap();
+        let db_pools = DbPools::with_replica(pool.clone(), replica);
+
+        let value: i32 = db_pools
+            .read_with_fallback(async |read| sqlx::query_scalar("SELECT 1").fetch_one(read).await)
+            .await
+            .unwrap();
+        assert_eq!(value, 1);
+        assert_eq!(db_pools.read_fallbacks(), 1);
+
+        // Configurable per call
+        let config = ReadFallbackConfig::new().disabled();
+        let result: Result<i32, _> = db_pools
+            .read_with_fallback_with(&config, async |read| {
+                sqlx::query_scalar("SELECT 1").fetch_one(read).await
+            })
+            .await;
+        assert!(matches!(result, Err(sqlx::Error::PoolTimedOut)));
+
+        let config = ReadFallbackConfig::new().when(|e| matches!(e, sqlx::Error::RowNotFound));
+        let mut attempts = 0;
+        let result: Result<(), _> = db_pools
+            .read_with_fallback_with(&config, async |_| {
+                attempts += 1;
+                Err(sqlx::Error::RowNotFound)
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 2);
+        assert_eq!(db_pools.read_fallbacks(), 2);
+
+        // Reads already on the primary aren't repeated
+        let single = DbPools::new(pool);
+        let mut attempts = 0;
+        let result: Result<(), _> = single
+            .read_with_fallback(async |_| {
+                attempts += 1;
+                Err(sqlx::Error::PoolTimedOut)
+            })
+            .await;
+        assert!(result.is_err());
+        assert_eq!(attempts, 1);
+        assert_eq!(single.read_fallbacks(), 0);
+    }
+
+    #[sqlx::test]
+    async fn test_routing_many_replicas(pool: PgPool) {
+        // More replicas than fit in the inline candidate buffer
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(); 20]);
+        let picks: Vec<_> = (0..20)
+            .map(|_| {
+                let read = db_pools.read();
+                (0..20).find(|&i| same_pool(read, db_pools.replica(i).unwrap()))
+            })
+            .collect();
+        assert_eq!(picks, (0..20).map(Some).collect::<Vec<_>>());
+    }
+
+    #[sqlx::test]
+    async fn test_custom_routing_policy(pool: PgPool) {
+        /// Reads from the last available replica, or the primary when the
+        /// first replica is drained.
+        #[derive(Debug, Default)]
+        struct Last {
+            seen: std::sync::Mutex<Vec<(u32, u32)>>,
+        }
+
+        impl RoutingPolicy for std::sync::Arc<Last> {
+            fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+                *self.seen.lock().unwrap() = candidates
+                    .iter()
+                    .map(|candidate| (candidate.weight(), candidate.configured_weight()))
+                    .collect();
+                if candidates[0].configured_weight() == 0 {
+                    return None;
+                }
+                candidates
+                    .iter()
+                    .rposition(|candidate| candidate.weight() > 0)
+            }
+        }
+
+        let policy = std::sync::Arc::new(Last::default());
+        let replicas = vec![
+            Replica::new(pool.clone()).weight(2),
+            Replica::new(pool.clone()),
+            Replica::new(pool.clone()),
+        ];
+        let db_pools =
+            DbPools::with_replicas(pool.clone(), replicas).routing_policy(policy.clone());
+
+        assert!(same_pool(db_pools.read(), db_pools.replica(2).unwrap()));
+        assert_eq!(*policy.seen.lock().unwrap(), vec![(2, 2), (1, 1), (1, 1)]);
+
+        db_pools.set_replica_weight(2, 0);
+        assert!(same_pool(db_pools.read(), db_pools.replica(1).unwrap()));
+        assert_eq!(*policy.seen.lock().unwrap(), vec![(2, 2), (1, 1), (0, 0)]);
+
+        // None reads from the primary
+        db_pools.set_replica_weight(0, 0);
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        // The built-in strategies are policies too
+        let db_pools = DbPools::with_replicas(pool.clone(), vec![pool.clone(), pool.clone()])
+            .routing_policy(RoundRobin::new());
+        let picks: Vec<bool> = (0..4)
+            .map(|_| same_pool(db_pools.read(), db_pools.replica(0).unwrap()))
+            .collect();
+        assert_eq!(picks, vec![true, false, true, false]);
+    }
+
+    #[sqlx::test]
+    async fn test_read_for_affinity_key(pool: PgPool) {
+        let named = |names: &[&str]| {
+            let replicas: Vec<_> = names
+                .iter()
+                .map(|name| Replica::new(pool.clone()).name(*name))
+                .collect();
+            DbPools::with_replicas(pool.clone(), replicas)
+        };
+        let mapping = |db_pools: &DbPools, names: &[&str]| -> Vec<Option<String>> {
+            (0..1000)
+                .map(|key: u64| {
+                    let read = db_pools.read_for(&key);
+                    (0..names.len())
+                        .find(|&i| same_pool(read, db_pools.replica(i).unwrap()))
+                        .map(|i| names[i].to_string())
+                })
+                .collect()
+        };
+
+        let three = ["a", "b", "c"];
+        let db_pools = named(&three);
+        let before = mapping(&db_pools, &three);
+        // The mapping is fixed by the hash algorithm, not the build
+        let pinned = ["c", "b", "c", "a", "b", "a", "b", "c"];
+        assert!(before
+            .iter()
+            .zip(pinned)
+            .all(|(b, p)| b.as_deref() == Some(p)));
+        assert_eq!(mapping(&db_pools, &three), before);
+        for name in three {
+            let share = before.iter().filter(|n| n.as_deref() == Some(name)).count();
+            assert!((250..420).contains(&share), "{name} got {share} keys");
+        }
+
+        // Adding a replica only moves keys onto it, about a quarter of them
+        let four = ["a", "b", "c", "d"];
+        let after = mapping(&named(&four), &four);
+        let moved: Vec<_> = before.iter().zip(&after).filter(|(b, a)| b != a).collect();
+        assert!(moved.iter().all(|(_, a)| a.as_deref() == Some("d")));
+        assert!(
+            (170..330).contains(&moved.len()),
+            "{} keys moved",
+            moved.len()
+        );
+
+        // Removing one, or taking it out of rotation, only moves its own keys
+        let two = ["a", "c"];
+        let after = mapping(&named(&two), &two);
+        assert!(before
+            .iter()
+            .zip(&after)
+            .all(|(b, a)| b.as_deref() == Some("b") || b == a));
+        db_pools.set_replica_weight(1, 0);
+        assert_eq!(mapping(&db_pools, &three), after);
+
+        // Primary when nothing is in rotation or inside a primary-reads scope
+        db_pools.set_replica_weight(0, 0);
+        db_pools.set_replica_weight(2, 0);
+        assert!(same_pool(db_pools.read_for(&1), db_pools.write()));
+        let db_pools = named(&three);
+        with_primary_reads(async {
+            assert!(same_pool(db_pools.read_for(&1), db_pools.write()));
+        })
+        .await;
+
+        // Keys are hashed from their bytes, however they're held
+        let tenant = String::from("tenant-42");
+        assert!(same_pool(
+            db_pools.read_for("tenant-42"),
+            db_pools.read_for(&tenant)
+        ));
+        assert!(same_pool(
+            db_pools.read_for("tenant-42"),
+            db_pools.read_for(tenant.as_bytes())
+        ));
+
+        // Reads the top replica's half-open breaker turns away go to the
+        // next-ranked replica, not the primary
+        let config = CircuitBreakerConfig::new()
+            .min_reads(1)
+            .cooldown(std::time::Duration::from_millis(100))
+            .trial_reads(3);
+        let db_pools = named(&["a", "b"]).circuit_breaker(config);
+        let top = db_pools.read_for(&7);
+        db_pools.record_read(
+            top,
+            std::time::Duration::ZERO,
+            Some(&sqlx::Error::PoolTimedOut),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        let barrier = std::sync::Barrier::new(16);
+        let reads: Vec<_> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        db_pools.read_for(&7)
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .collect()
+        });
+        assert_eq!(
+            reads.iter().filter(|read| same_pool(**read, top)).count(),
+            3
+        );
+        assert!(reads.iter().all(|read| !same_pool(*read, db_pools.write())));
+    }
+
+    #[sqlx::test]
+    async fn test_zone_affinity(pool: PgPool) {
+        let replicas = vec![
+            Replica::new(pool.clone()).zone("zone-a"),
+            Replica::new(pool.clone()).zone("zone-b"),
+            Replica::new(pool.clone()).zone("zone-a"),
+            Replica::new(pool.clone()),
+        ];
+        let db_pools = DbPools::with_replicas(pool.clone(), replicas)
+            .routing_policy(ZoneAffinity::new("zone-a"));
+        let picked = |db_pools: &DbPools| {
+            let read = db_pools.read();
+            (0..4).find(|&i| same_pool(read, db_pools.replica(i).unwrap()))
+        };
+
+        // Same-zone replicas only, in round-robin order
+        let picks: Vec<_> = (0..4).map(|_| picked(&db_pools)).collect();
+        assert_eq!(picks, vec![Some(0), Some(2), Some(0), Some(2)]);
+
+        // Other zones, including untagged replicas, once the local zone is drained
+        db_pools.set_replica_weight(0, 0);
+        assert_eq!(picked(&db_pools), Some(2));
+        db_pools.set_replica_weight(2, 0);
+        let mut picks: Vec<_> = (0..4).map(|_| picked(&db_pools)).collect();
+        picks.sort();
+        assert_eq!(picks, vec![Some(1), Some(1), Some(3), Some(3)]);
+
+        // Then the primary
+        db_pools.set_replica_weight(1, 0);
+        db_pools.set_replica_weight(3, 0);
+        assert_eq!(picked(&db_pools), None);
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        // An unset variable means no local zone, so every replica is in the
+        // same tier
+        assert_eq!(ZoneAffinity::new("zone-b").local_zone(), Some("zone-b"));
+        let policy = ZoneAffinity::from_env_var("SQLX_POOL_ROUTER_TEST_ZONE_NEVER_SET");
+        assert_eq!(policy.local_zone(), None);
+        for index in 0..4 {
+            db_pools.set_replica_weight(index, 1);
+        }
+        let db_pools = db_pools.routing_policy(policy);
+        let mut picks: Vec<_> = (0..4).map(|_| picked(&db_pools)).collect();
+        picks.sort();
+        assert_eq!(picks, vec![Some(0), Some(1), Some(2), Some(3)]);
+    }
+
+    #[sqlx::test]
+    async fn test_least_latency_load_balance(pool: PgPool) {
+        let replicas = vec![separate_pool(&pool).await, separate_pool(&pool).await];
+        let db_pools =
+            DbPools::with_replicas(pool.clone(), replicas).load_balance(LoadBalance::LeastLatency);
+        let (slow, fast) = (
+            ReadPool::new(db_pools.replica(0).unwrap()),
+            ReadPool::new(db_pools.replica(1).unwrap()),
+        );
+
+        // Unmeasured replicas are preferred so they get measured
+        let ms = std::time::Duration::from_millis;
+        db_pools.record_read(slow, ms(100), None);
+        assert!((0..20).all(|_| same_pool(db_pools.read(), fast)));
+
+        // The moving average favors the faster replica
+        db_pools.record_read(fast, ms(5), None);
+        db_pools.record_read(slow, ms(200), None);
+        assert_eq!(db_pools.replica_latency(0), Some(ms(120)));
+        assert_eq!(db_pools.replica_latency(1), Some(ms(5)));
+        assert!((0..20).all(|_| same_pool(db_pools.read(), fast)));
+
+        // Connection errors say nothing about query latency
+        db_pools.record_read(fast, ms(1000), Some(&sqlx::Error::PoolTimedOut));
+        assert_eq!(db_pools.replica_latency(1), Some(ms(5)));
+
+        // Closure helpers feed the average too
+        for _ in 0..10 {
+            db_pools
+                .read_with_fallback(async |read| {
+                    if same_pool(read, fast) {
+                        sqlx::query("SELECT pg_sleep(0.3)").execute(read).await?;
+                    }
+                    Ok(())
+                })
+                .await
+                .unwrap();
+        }
+        assert!(db_pools.replica_latency(1).unwrap() > db_pools.replica_latency(0).unwrap());
+        assert!(same_pool(db_pools.read(), slow));
+
+        // Replicas out of rotation are never picked, however fast
+        db_pools.set_replica_weight(0, 0);
+        assert!((0..20).all(|_| same_pool(db_pools.read(), fast)));
+        assert_eq!(db_pools.replica_latency(2), None);
+    }
+
+    #[sqlx::test]
+    async fn test_spill_to_primary(pool: PgPool) {
+        let replica = PgPoolOptions::new()
+            .max_connections(1)
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap();
+        let db_pools = DbPools::with_replica(separate_pool(&pool).await, replica.clone())
+            .spill_to_primary(SpilloverConfig::new().headroom(1));
+        let (primary, replica): (&PgPool, _) = (&db_pools, db_pools.replica(0).unwrap());
+
+        // Warm up both primary connections so they sit idle
+        let warm = (
+            primary.acquire().await.unwrap(),
+            primary.acquire().await.unwrap(),
+        );
+        drop(warm);
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(primary.num_idle(), 2);
+
+        // Replica has an idle connection: no spill
+        sqlx::query("SELECT 1").execute(replica).await.unwrap();
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        assert_eq!(replica.num_idle(), 1);
+        assert!(same_pool(db_pools.read(), replica));
+
+        // Replica saturated, primary above the headroom: spill
+        let held = replica.acquire().await.unwrap();
+        assert!(same_pool(db_pools.read(), primary));
+        assert_eq!(db_pools.read_spills(), 1);
+
+        // Primary at the headroom: stay on the replica and queue
+        let busy = primary.acquire().await.unwrap();
+        assert!(same_pool(db_pools.read(), replica));
+        assert_eq!(db_pools.read_spills(), 1);
+        drop((held, busy));
+
+        // Off by default
+        let db_pools = DbPools::with_replica(primary.clone(), replica.clone());
+        let _held = replica.acquire().await.unwrap();
+        assert!(same_pool(db_pools.read(), db_pools.replica(0).unwrap()));
+    }
+
+    #[sqlx::test]
+    async fn test_circuit_breaker(pool: PgPool) {
+        // A replica whose only connection is taken times out on acquire
+        let replica = PgPoolOptions::new()
+            .max_connections(1)
+            .acquire_timeout(std::time::Duration::from_millis(50))
+            .connect_with(pool.connect_options().as_ref().clone())
+            .await
+            .unwrap();
+        let held = replica.acquire().await.unwrap();
+        let config = CircuitBreakerConfig::new()
+            .window(2)
+            .min_reads(2)
+            .cooldown(std::time::Duration::from_millis(200))
+            .trial_reads(1);
+        let db_pools = DbPools::with_replica(pool.clone(), replica).circuit_breaker(config);
+        let replica = db_pools.replica(0).unwrap();
+
+        for _ in 0..2 {
+            let value: i32 = db_pools
+                .read_with_fallback(async |read| {
+                    sqlx::query_scalar("SELECT 1").fetch_one(read).await
+                })
+                .await
+                .unwrap();
+            assert_eq!(value, 1);
+        }
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::Open]);
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        // After the cooldown a single trial read goes to the replica
+        tokio::time::sleep(std::time::Duration::from_millis(250)).await;
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::HalfOpen]);
+        let trial = db_pools.read();
+        assert!(same_pool(trial, replica));
+        assert!(same_pool(db_pools.read(), db_pools.write()));
+
+        drop(held);
+        let started = std::time::Instant::now();
+        let result = sqlx::query("SELECT 1").execute(trial).await;
+        db_pools.record_read(trial, started.elapsed(), result.as_ref().err());
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::Closed]);
+        assert!(same_pool(db_pools.read(), replica));
+
+        // Slow reads trip the breaker too; other errors don't
+        let config = CircuitBreakerConfig::new()
+            .min_reads(2)
+            .slow_read(std::time::Duration::from_millis(100));
+        let db_pools =
+            DbPools::with_replica(pool.clone(), separate_pool(&pool).await).circuit_breaker(config);
+        let read = db_pools.read();
+        db_pools.record_read(
+            read,
+            std::time::Duration::ZERO,
+            Some(&sqlx::Error::RowNotFound),
+        );
+        db_pools.record_read(read, std::time::Duration::ZERO, None);
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::Closed]);
+        db_pools.record_read(read, std::time::Duration::from_secs(1), None);
+        db_pools.record_read(read, std::time::Duration::from_secs(1), None);
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::Open]);
+
+        // More required reads than the window holds still lets the circuit open
+        let db_pools = DbPools::with_replica(pool.clone(), separate_pool(&pool).await)
+            .circuit_breaker(CircuitBreakerConfig::new().window(4).min_reads(30));
+        for _ in 0..4 {
+            db_pools.record_read(
+                db_pools.read(),
+                std::time::Duration::ZERO,
+                Some(&sqlx::Error::PoolTimedOut),
+            );
+        }
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::Open]);
+
+        // A half-open circuit lets exactly `trial_reads` concurrent reads through
+        let config = CircuitBreakerConfig::new()
+            .min_reads(1)
+            .cooldown(std::time::Duration::from_millis(100))
+            .trial_reads(3);
+        let db_pools =
+            DbPools::with_replica(pool.clone(), separate_pool(&pool).await).circuit_breaker(config);
+        let replica = db_pools.replica(0).unwrap();
+        db_pools.record_read(
+            ReadPool::new(replica),
+            std::time::Duration::ZERO,
+            Some(&sqlx::Error::PoolTimedOut),
+        );
+        tokio::time::sleep(std::time::Duration::from_millis(150)).await;
+        assert_eq!(db_pools.replica_circuits(), vec![CircuitState::HalfOpen]);
+        let barrier = std::sync::Barrier::new(16);
+        let trials = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..16)
+                .map(|_| {
+                    scope.spawn(|| {
+                        barrier.wait();
+                        same_pool(db_pools.read(), replica)
+                    })
+                })
+                .collect();
+            threads
+                .into_iter()
+                .map(|thread| thread.join().unwrap())
+                .filter(|&trial| trial)
+                .count()
+        });
+        assert_eq!(trials, 3);
+    }
+
+    #[sqlx::test]
+    async fn test_hedged_read(pool: PgPool) {
+        let replicas = vec![separate_pool(&pool).await, separate_pool(&pool).await];
+        let db_pools = DbPools::with_replicas(pool.clone(), replicas);
+        let slow = db_pools.replica(0).unwrap();
+        let config = HedgeConfig::new().initial_delay(std::time::Duration::from_millis(20));
+
+        // Round-robin starts on the slow replica, so the hedge answers first
+        let started = std::time::Instant::now();
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        let answered_by = db_pools
+            .hedged_read_with(&config, async |read| {
+                calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                if same_pool(read, slow) {
+                    sqlx::query("SELECT pg_sleep(5)").execute(read).await?;
+                    return Ok("slow");
+                }
+                Ok("fast")
+            })
+            .await
+            .unwrap();
+        assert_eq!(answered_by, "fast");
+        assert_eq!(calls.into_inner(), 2);
+        assert!(started.elapsed() < std::time::Duration::from_secs(2));
+
+        // Fast reads never send a duplicate
+        let calls = std::sync::atomic::AtomicUsize::new(0);
+        for _ in 0..4 {
+            let value: i32 = db_pools
+                .hedged_read_with(&config, async |read| {
+                    calls.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                    sqlx::query_scalar("SELECT 1").fetch_one(read).await
+                })
+                .await
+                .unwrap();
+            assert_eq!(value, 1);
+        }
+        assert_eq!(calls.into_inner(), 4);
+
+        // With enough samples the delay follows recorded latencies
+        assert_eq!(
+            db_pools.hedge_delay(&config),
+            std::time::Duration::from_millis(20)
+        );
+        let config = config.min_samples(5).percentile(1.0);
+        assert!(db_pools.hedge_delay(&config) >= std::time::Duration::from_millis(20));
+        let config = config.percentile(0.0);
+        assert!(db_pools.hedge_delay(&config) < std::time::Duration::from_millis(20));
+
+        // Round-robin is back on the second replica; when the hedge fails,
+        // the first read is still awaited
+        let value = db_pools
+            .hedged_read_with(&config.min_delay(std::time::Duration::ZERO), async |read| {
+                if same_pool(read, slow) {
+                    return Err(sqlx::Error::RowNotFound);
+                }
+                sqlx::query("SELECT pg_sleep(0.1)").execute(read).await?;
+                Ok("first")
+            })
+            .await
+            .unwrap();
+        assert_eq!(value, "first");
+    }
+
+    #[sqlx::test]
+    async fn test_read_and_write_executors(pool: PgPool) {
+        use sqlx::{Acquire, Postgres};
+
+        async fn read_only_setting<'c>(db: impl Acquire<'c, Database = Postgres>) -> String {
+            let mut conn = db.acquire().await.unwrap();
+            sqlx::query_scalar("SHOW default_transaction_read_only")
+                .fetch_one(&mut *conn)
+                .await
+                .unwrap()
+        }
+
+        let pools = TestDbPools::new(pool).await.unwrap();
+        assert_eq!(read_only_setting(pools.read_executor()).await, "on");
+        assert_eq!(read_only_setting(pools.write_executor()).await, "off");
+
+        // As executors
+        let result = sqlx::query("CREATE TABLE executor_rejected (id INT)")
+            .execute(pools.read_executor())
+            .await;
+        assert!(result.is_err());
+        sqlx::query("CREATE TABLE executor_items (id INT)")
+            .execute(pools.write_executor())
+            .await
+            .unwrap();
+
+        // Transactions
+        let mut tx = pools.write_executor().begin().await.unwrap();
+        sqlx::query("INSERT INTO executor_items VALUES (1)")
+            .execute(&mut *tx)
+            .await
+            .unwrap();
+        tx.commit().await.unwrap();
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM executor_items")
+            .fetch_one(pools.read_executor())
+            .await
+            .unwrap();
+        assert_eq!(count, 1);
+
+        // The read pool is resolved when used, so scoped overrides apply
+        let reader = pools.read_executor();
+        let setting = with_primary_reads(read_only_setting(reader)).await;
+        assert_eq!(setting, "off");
+    }
+
     #[sqlx::test]
     async fn test_dbpools_close(pool: PgPool) {
         let db_pools = DbPools::new(pool);
@@ -734,7 +3108,7 @@ mod tests {
     #[sqlx::test]
     async fn test_pgpool_implements_pool_provider(pool: PgPool) {
         // PgPool should implement PoolProvider
-        assert_eq!(pool.read() as *const _, pool.write() as *const _);
+        assert!(same_pool(pool.read(), pool.write()));
 
         // Should be able to use it the same way
         let result: (i32,) = sqlx::query_as("SELECT 1")
@@ -0,0 +1,198 @@
+//! Replica configuration and per-replica routing state.
+
+use crate::affinity::name_hash;
+use crate::balance::LatencyEwma;
+use crate::circuit::Breaker;
+use crate::health::HealthState;
+use crate::lag::LagState;
+use sqlx::PgPool;
+use std::future::Future;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::task::JoinHandle;
+
+/// A replica pool plus the routing options that apply to it.
+///
+/// Anything that takes replicas accepts either a plain [`PgPool`] (which gets
+/// the defaults) or a `Replica` built with this type.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx::PgPool;
+/// use sqlx_pool_router::{DbPools, Replica};
+///
+/// # async fn example() -> Result<(), sqlx::Error> {
+/// let primary = PgPool::connect("postgresql://primary/db").await?;
+/// let large = PgPool::connect("postgresql://replica-large/db").await?;
+/// let small = PgPool::connect("postgresql://replica-small/db").await?;
+///
+/// // The large replica receives three times as many reads as the small one
+/// let pools = DbPools::with_replicas(
+///     primary,
+///     vec![Replica::new(large).weight(3), Replica::new(small).weight(1)],
+/// );
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Replica {
+    pool: PgPool,
+    weight: u32,
+    expect_recovery: bool,
+    zone: Option<String>,
+    name: Option<String>,
+}
+
+impl Replica {
+    /// Weight given to replicas that don't set one explicitly.
+    pub const DEFAULT_WEIGHT: u32 = 1;
+
+    /// Wrap a replica pool with default options.
+    pub fn new(pool: PgPool) -> Self {
+        Self {
+            pool,
+            weight: Self::DEFAULT_WEIGHT,
+            expect_recovery: true,
+            zone: None,
+            name: None,
+        }
+    }
+
+    /// Set the relative share of reads this replica receives.
+    ///
+    /// Reads are spread in proportion to the weights of all replicas. A weight
+    /// of `0` takes the replica out of rotation entirely. Weights can be
+    /// changed later with [`DbPools::set_replica_weight`](crate::DbPools::set_replica_weight).
+    pub fn weight(mut self, weight: u32) -> Self {
+        self.weight = weight;
+        self
+    }
+
+    /// Tag the replica with the availability zone it runs in.
+    ///
+    /// Used by [`ZoneAffinity`](crate::ZoneAffinity) to keep reads in the
+    /// caller's zone, and visible to custom policies through
+    /// [`Candidate::zone`](crate::Candidate::zone).
+    pub fn zone(mut self, zone: impl Into<String>) -> Self {
+        self.zone = Some(zone.into());
+        self
+    }
+
+    /// Set a stable name for the replica.
+    ///
+    /// [`DbPools::read_for`](crate::DbPools::read_for) hashes affinity keys
+    /// against replica names, so a key keeps mapping to the same replica
+    /// when other replicas are added or removed. Defaults to
+    /// `host:port/database` from the pool's connect options; set a name when
+    /// several replicas share those, for example behind a proxy.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = Some(name.into());
+        self
+    }
+
+    /// Don't require this replica to be in recovery, or to be part of the
+    /// primary's cluster, during [`DbPools::validate`](crate::DbPools::validate).
+    /// Its database name is still checked.
+    ///
+    /// For read pools that deliberately point at a writable server, such as
+    /// a logical replication subscriber or the primary itself in development.
+    pub fn skip_recovery_check(mut self) -> Self {
+        self.expect_recovery = false;
+        self
+    }
+}
+
+impl From<PgPool> for Replica {
+    fn from(pool: PgPool) -> Self {
+        Self::new(pool)
+    }
+}
+
+/// Runtime state for a single replica, shared between clones of `DbPools`.
+#[derive(Debug)]
+pub(crate) struct ReplicaNode {
+    pub(crate) pool: PgPool,
+    pub(crate) expect_recovery: bool,
+    pub(crate) zone: Option<String>,
+    /// FNV-1a state after the replica's name, for rendezvous hashing.
+    pub(crate) name_hash: u64,
+    weight: AtomicU32,
+    pub(crate) health: HealthState,
+    pub(crate) lag: LagState,
+    pub(crate) breaker: Breaker,
+    pub(crate) latency: LatencyEwma,
+}
+
+impl ReplicaNode {
+    pub(crate) fn weight(&self) -> u32 {
+        self.weight.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn set_weight(&self, weight: u32) {
+        self.weight.store(weight, Ordering::Relaxed);
+    }
+
+    /// Weight used for routing decisions; `0` means the replica is skipped.
+    ///
+    /// Unhealthy replicas, replicas lagging beyond the configured limit and
+    /// replicas with an open circuit have an effective weight of `0`
+    /// regardless of their configured weight.
+    pub(crate) fn effective_weight(&self) -> u32 {
+        if !self.health.is_healthy() || !self.lag.is_within_limit() || !self.breaker.is_available()
+        {
+            return 0;
+        }
+        self.weight()
+    }
+}
+
+/// Run `round` every `interval` until every clone of the replica set is dropped.
+pub(crate) fn spawn_monitor<F, Fut>(
+    replicas: &Arc<[ReplicaNode]>,
+    interval: Duration,
+    mut round: F,
+) -> JoinHandle<()>
+where
+    F: FnMut(Arc<[ReplicaNode]>) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send,
+{
+    let replicas = Arc::downgrade(replicas);
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            ticker.tick().await;
+            let Some(replicas) = replicas.upgrade() else {
+                break;
+            };
+            round(replicas).await;
+        }
+    })
+}
+
+impl From<Replica> for ReplicaNode {
+    fn from(replica: Replica) -> Self {
+        let name = replica.name.unwrap_or_else(|| {
+            let options = replica.pool.connect_options();
+            format!(
+                "{}:{}/{}",
+                options.get_host(),
+                options.get_port(),
+                options.get_database().unwrap_or_default()
+            )
+        });
+        Self {
+            name_hash: name_hash(&name),
+            pool: replica.pool,
+            expect_recovery: replica.expect_recovery,
+            zone: replica.zone,
+            weight: AtomicU32::new(replica.weight),
+            health: HealthState::default(),
+            lag: LagState::default(),
+            breaker: Breaker::default(),
+            latency: LatencyEwma::default(),
+        }
+    }
+}
@@ -0,0 +1,535 @@
+//! Retrying transactions that fail on transient conflicts.
+
+use crate::context::primary_reads_forced;
+use crate::hedge::LatencyWindow;
+use crate::transaction::begin_write;
+use crate::{DbPools, IsolationLevel, PoolProvider, ReadPool, TransactionOptions};
+use sqlx::{Postgres, Transaction};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Whether an error is a transient conflict that succeeds when retried.
+///
+/// Matches serialization failures (SQLSTATE `40001`) and deadlocks
+/// (`40P01`). The whole transaction has to be retried, not just the failing
+/// statement.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx_pool_router::{is_transaction_conflict, DbPools, PoolProvider};
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let result = sqlx::query("UPDATE accounts SET balance = balance - 10 WHERE id = 1")
+///     .execute(pools.write())
+///     .await;
+/// if let Err(e) = &result {
+///     if is_transaction_conflict(e) {
+///         // try again
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub fn is_transaction_conflict(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db) => db
+            .code()
+            .is_some_and(|code| matches!(&*code, "40001" | "40P01")),
+        _ => false,
+    }
+}
+
+/// Whether an error is a query cancelled by a hot standby's recovery.
+///
+/// A standby cancels queries that hold back WAL replay for longer than
+/// `max_standby_streaming_delay`, with "canceling statement due to conflict
+/// with recovery". These are reported as serialization failures (SQLSTATE
+/// `40001`) or deadlocks (`40P01`), so without knowing which server failed
+/// both the code and the message are checked: ordinary serialization
+/// failures and deadlocks, which mean the transaction itself must be
+/// retried, aren't matched (see [`is_transaction_conflict`]). Running the
+/// query again, preferably on another server, usually succeeds.
+///
+/// The message is translated when the server's `lc_messages` isn't English,
+/// so this only recognizes conflicts from English-language servers.
+/// [`DbPools::retry_read`] also knows which pool failed, and treats any
+/// `40001` or `40P01` from a replica as a recovery conflict.
+pub fn is_recovery_conflict(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Database(db) => {
+            db.code()
+                .is_some_and(|code| matches!(&*code, "40001" | "40P01"))
+                && db.message().contains("conflict with recovery")
+        }
+        _ => false,
+    }
+}
+
+/// Whether an error means the pool couldn't reach its server.
+///
+/// Matches pool acquire timeouts, I/O errors and TLS errors: failures of
+/// the connection rather than of the query.
+pub fn is_connection_error(error: &sqlx::Error) -> bool {
+    matches!(
+        error,
+        sqlx::Error::PoolTimedOut | sqlx::Error::Io(_) | sqlx::Error::Tls(_)
+    )
+}
+
+/// Counters for the read helpers, shared between clones of a [`DbPools`].
+#[derive(Debug, Default)]
+pub(crate) struct ReadStats {
+    retries: AtomicU64,
+    fallbacks: AtomicU64,
+    pub(crate) spills: AtomicU64,
+    pub(crate) hedge_latency: LatencyWindow,
+}
+
+/// Settings for [`DbPools::read_with_fallback_with`].
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::{is_connection_error, ReadFallbackConfig};
+///
+/// // Also fall back when the replica pool was closed
+/// let config = ReadFallbackConfig::new()
+///     .when(|e| is_connection_error(e) || matches!(e, sqlx::Error::PoolClosed));
+///
+/// // Protect the primary on this endpoint
+/// let no_fallback = ReadFallbackConfig::new().disabled();
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReadFallbackConfig {
+    when: Option<fn(&sqlx::Error) -> bool>,
+}
+
+impl ReadFallbackConfig {
+    /// Create a config that falls back on errors [`is_connection_error`] matches.
+    pub fn new() -> Self {
+        Self {
+            when: Some(is_connection_error),
+        }
+    }
+
+    /// Fall back to the primary on errors `predicate` matches.
+    pub fn when(mut self, predicate: fn(&sqlx::Error) -> bool) -> Self {
+        self.when = Some(predicate);
+        self
+    }
+
+    /// Never fall back; return the replica's error.
+    pub fn disabled(mut self) -> Self {
+        self.when = None;
+        self
+    }
+
+    fn falls_back_on(&self, error: &sqlx::Error) -> bool {
+        self.when.is_some_and(|predicate| predicate(error))
+    }
+}
+
+impl Default for ReadFallbackConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Settings for [`DbPools::retry_read_with`].
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::ReadRetryConfig;
+///
+/// // Try two replicas, then the primary
+/// let config = ReadRetryConfig::new().max_attempts(3).primary_after(2);
+/// ```
+#[derive(Clone, Debug)]
+pub struct ReadRetryConfig {
+    max_attempts: u32,
+    primary_after: u32,
+}
+
+impl ReadRetryConfig {
+    /// Create a config with the defaults: up to 3 attempts, moving to the
+    /// primary after 2 failures.
+    pub fn new() -> Self {
+        Self {
+            max_attempts: 3,
+            primary_after: 2,
+        }
+    }
+
+    /// Total number of attempts, including the first.
+    ///
+    /// Values below `1` are treated as `1`.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Number of recovery conflicts after which the read is retried on the
+    /// primary instead of another replica.
+    ///
+    /// Values below `1` are treated as `1`, which sends the first retry to
+    /// the primary.
+    pub fn primary_after(mut self, failures: u32) -> Self {
+        self.primary_after = failures.max(1);
+        self
+    }
+}
+
+impl Default for ReadRetryConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Settings for [`DbPools::write_tx_with`].
+///
+/// A failed attempt is retried after a backoff that starts at
+/// [`backoff`](Self::backoff) and doubles each time up to
+/// [`max_backoff`](Self::max_backoff). Each delay is randomized between half
+/// and all of its value so that conflicting transactions don't retry in
+/// lockstep.
+///
+/// # Example
+///
+/// ```rust
+/// use std::time::Duration;
+/// use sqlx_pool_router::{IsolationLevel, WriteTxConfig};
+///
+/// let config = WriteTxConfig::new()
+///     .isolation(IsolationLevel::Serializable)
+///     .max_attempts(5)
+///     .backoff(Duration::from_millis(20));
+/// ```
+#[derive(Clone, Debug)]
+pub struct WriteTxConfig {
+    isolation: Option<IsolationLevel>,
+    max_attempts: u32,
+    backoff: Duration,
+    max_backoff: Duration,
+}
+
+impl WriteTxConfig {
+    /// Create a config with the defaults: the server's default isolation
+    /// level, up to 3 attempts, and a backoff from 10ms up to 1 second.
+    pub fn new() -> Self {
+        Self {
+            isolation: None,
+            max_attempts: 3,
+            backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+
+    /// Isolation level for the transaction.
+    pub fn isolation(mut self, isolation: IsolationLevel) -> Self {
+        self.isolation = Some(isolation);
+        self
+    }
+
+    /// Total number of attempts, including the first.
+    ///
+    /// Values below `1` are treated as `1`.
+    pub fn max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Delay before the first retry.
+    pub fn backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Upper bound for the delay between retries.
+    pub fn max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+
+    /// Delay before retrying after failed attempt number `attempt` (from 1).
+    fn delay(&self, attempt: u32) -> Duration {
+        let factor = 2u32.saturating_pow(attempt - 1);
+        jitter(self.backoff.saturating_mul(factor).min(self.max_backoff))
+    }
+}
+
+impl Default for WriteTxConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A random duration between half of `delay` and `delay`.
+pub(crate) fn jitter(delay: Duration) -> Duration {
+    let nanos = delay.as_nanos().min(u64::MAX as u128) as u64;
+    Duration::from_nanos(nanos / 2 + fastrand::u64(..=nanos - nanos / 2))
+}
+
+impl DbPools {
+    /// Run `f` in a transaction on the primary, retrying on conflicts.
+    ///
+    /// Same as [`write_tx_with`](Self::write_tx_with) with the default
+    /// [`WriteTxConfig`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// let balance: i64 = pools
+    ///     .write_tx(async |tx| {
+    ///         sqlx::query("UPDATE accounts SET balance = balance - 10 WHERE id = 1")
+    ///             .execute(&mut **tx)
+    ///             .await?;
+    ///         sqlx::query_scalar("SELECT balance FROM accounts WHERE id = 1")
+    ///             .fetch_one(&mut **tx)
+    ///             .await
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn write_tx<T, F>(&self, f: F) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'static, Postgres>) -> Result<T, sqlx::Error>,
+    {
+        self.write_tx_with(&WriteTxConfig::new(), f).await
+    }
+
+    /// Run `f` in a transaction on the primary, retrying on conflicts.
+    ///
+    /// The transaction is committed when `f` returns `Ok` and rolled back
+    /// when it returns `Err`. If `f` or the commit fails with an error that
+    /// [`is_transaction_conflict`] recognizes, the whole transaction is run
+    /// again in a fresh transaction, up to
+    /// [`max_attempts`](WriteTxConfig::max_attempts) times. Other errors, and
+    /// the last conflict, are returned as is.
+    ///
+    /// `f` may run more than once, so it shouldn't have side effects outside
+    /// the transaction.
+    pub async fn write_tx_with<T, F>(
+        &self,
+        config: &WriteTxConfig,
+        mut f: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'static, Postgres>) -> Result<T, sqlx::Error>,
+    {
+        let mut options = TransactionOptions::new();
+        if let Some(isolation) = config.isolation {
+            options = options.isolation(isolation);
+        }
+
+        let mut attempt = 1;
+        loop {
+            match self.write_tx_attempt(options, &mut f).await {
+                Err(e) if attempt < config.max_attempts && is_transaction_conflict(&e) => {
+                    tokio::time::sleep(config.delay(attempt)).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Run a read, retrying it elsewhere if a standby cancels it.
+    ///
+    /// Same as [`retry_read_with`](Self::retry_read_with) with the default
+    /// [`ReadRetryConfig`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// let totals: Vec<(i64, i64)> = pools
+    ///     .retry_read(async |pool| {
+    ///         sqlx::query_as("SELECT customer_id, SUM(total)::int8 FROM orders GROUP BY 1")
+    ///             .fetch_all(pool)
+    ///             .await
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn retry_read<T, F>(&self, f: F) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&ReadPool) -> Result<T, sqlx::Error>,
+    {
+        self.retry_read_with(&ReadRetryConfig::new(), f).await
+    }
+
+    /// Run a read, retrying it elsewhere if a standby cancels it.
+    ///
+    /// `f` first runs on [`read()`](PoolProvider::read). When it fails with
+    /// a serialization failure or deadlock (`40001` or `40P01`) on a
+    /// replica, which is how standbys report recovery conflicts, or any
+    /// error that [`is_recovery_conflict`] recognizes, it is run again on
+    /// a replica it hasn't tried yet, or on the primary once
+    /// [`primary_after`](ReadRetryConfig::primary_after) attempts have failed
+    /// or no untried replica is in rotation. Inside a
+    /// [`with_primary_reads`](crate::with_primary_reads) scope every attempt
+    /// runs on the primary. Other errors, and the last
+    /// conflict after [`max_attempts`](ReadRetryConfig::max_attempts), are
+    /// returned as is.
+    ///
+    /// Every retry is counted in [`read_retries`](Self::read_retries).
+    pub async fn retry_read_with<T, F>(
+        &self,
+        config: &ReadRetryConfig,
+        mut f: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&ReadPool) -> Result<T, sqlx::Error>,
+    {
+        let mut pool = self.read();
+        let mut tried = Vec::new();
+        let mut attempt = 1;
+        loop {
+            match self.observe(pool, f(pool)).await {
+                Err(e) if attempt < config.max_attempts && self.cancelled_by_recovery(pool, &e) => {
+                    self.read_stats.retries.fetch_add(1, Ordering::Relaxed);
+                    tried.extend(self.replica_index(pool));
+                    pool = if attempt >= config.primary_after || primary_reads_forced() {
+                        ReadPool::new(self.primary.current())
+                    } else {
+                        self.untried_replica(&tried)
+                            .unwrap_or_else(|| ReadPool::new(self.primary.current()))
+                    };
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Whether `error`, returned by a read on `pool`, is a recovery conflict.
+    ///
+    /// Standbys don't run serializable transactions, so a `40001` or
+    /// `40P01` from one can only come from recovery, whatever the language
+    /// of its message.
+    fn cancelled_by_recovery(&self, pool: &ReadPool, error: &sqlx::Error) -> bool {
+        is_recovery_conflict(error)
+            || (is_transaction_conflict(error) && self.replica_index(pool).is_some())
+    }
+
+    /// Number of reads retried by [`retry_read`](Self::retry_read) and
+    /// [`retry_read_with`](Self::retry_read_with) since the pools were
+    /// created, shared between clones.
+    ///
+    /// Export it as a counter to see how often standbys cancel queries.
+    pub fn read_retries(&self) -> u64 {
+        self.read_stats.retries.load(Ordering::Relaxed)
+    }
+
+    /// Run a read, falling back to the primary if the replica can't be reached.
+    ///
+    /// Same as [`read_with_fallback_with`](Self::read_with_fallback_with)
+    /// with the default [`ReadFallbackConfig`].
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx_pool_router::DbPools;
+    ///
+    /// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+    /// let name: String = pools
+    ///     .read_with_fallback(async |pool| {
+    ///         sqlx::query_scalar("SELECT name FROM users WHERE id = $1")
+    ///             .bind(1_i64)
+    ///             .fetch_one(pool)
+    ///             .await
+    ///     })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn read_with_fallback<T, F>(&self, f: F) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&ReadPool) -> Result<T, sqlx::Error>,
+    {
+        self.read_with_fallback_with(&ReadFallbackConfig::new(), f)
+            .await
+    }
+
+    /// Run a read, falling back to the primary if the replica can't be reached.
+    ///
+    /// `f` runs on [`read()`](PoolProvider::read). If that was a replica and
+    /// `f` fails with an error the config matches (by default, one that
+    /// [`is_connection_error`] recognizes), `f` is run once more on the
+    /// primary and its result returned. Fallbacks are counted in
+    /// [`read_fallbacks`](Self::read_fallbacks).
+    pub async fn read_with_fallback_with<T, F>(
+        &self,
+        config: &ReadFallbackConfig,
+        mut f: F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&ReadPool) -> Result<T, sqlx::Error>,
+    {
+        let pool = self.read();
+        match self.observe(pool, f(pool)).await {
+            Err(e) if config.falls_back_on(&e) && self.replica_index(pool).is_some() => {
+                self.read_stats.fallbacks.fetch_add(1, Ordering::Relaxed);
+                f(ReadPool::new(self.primary.current())).await
+            }
+            result => result,
+        }
+    }
+
+    /// Number of reads [`read_with_fallback`](Self::read_with_fallback) and
+    /// [`read_with_fallback_with`](Self::read_with_fallback_with) sent to the
+    /// primary since the pools were created, shared between clones.
+    pub fn read_fallbacks(&self) -> u64 {
+        self.read_stats.fallbacks.load(Ordering::Relaxed)
+    }
+
+    /// Position of the replica `pool` refers to, if it is one.
+    pub(crate) fn replica_index(&self, pool: &ReadPool) -> Option<usize> {
+        self.replicas
+            .iter()
+            .position(|replica| std::ptr::eq(&replica.pool, pool.pool()))
+    }
+
+    /// The next in-rotation replica after the last one tried that isn't in `tried`.
+    pub(crate) fn untried_replica(&self, tried: &[usize]) -> Option<&ReadPool> {
+        let start = tried.last().map_or(0, |index| index + 1);
+        (start..self.replicas.len())
+            .chain(0..start)
+            .find(|index| {
+                let replica = &self.replicas[*index];
+                !tried.contains(index) && replica.effective_weight() > 0 && self.admits(replica)
+            })
+            .map(|index| ReadPool::new(&self.replicas[index].pool))
+    }
+
+    async fn write_tx_attempt<T, F>(
+        &self,
+        options: TransactionOptions,
+        f: &mut F,
+    ) -> Result<T, sqlx::Error>
+    where
+        F: AsyncFnMut(&mut Transaction<'static, Postgres>) -> Result<T, sqlx::Error>,
+    {
+        let mut tx = begin_write(self, options).await?;
+        match f(&mut tx).await {
+            Ok(value) => {
+                tx.commit().await?;
+                Ok(value)
+            }
+            Err(e) => {
+                // The transaction is rolled back when dropped even if this fails
+                let _ = tx.rollback().await;
+                Err(e)
+            }
+        }
+    }
+}
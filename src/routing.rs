@@ -0,0 +1,208 @@
+//! The extension point for choosing which replica serves a read.
+
+use crate::balance::in_use;
+use crate::replica::ReplicaNode;
+use crate::{CircuitState, DbPools};
+use sqlx::PgPool;
+use std::fmt;
+use std::time::Duration;
+
+/// Decides which replica [`DbPools::read`](crate::PoolProvider::read) uses.
+///
+/// `select` receives the replicas a read may go to, with the health, lag,
+/// load and latency `DbPools` tracks for each. For
+/// [`read()`](crate::PoolProvider::read) that is every configured replica,
+/// in the order they were provided; for
+/// [`read_after_lsn`](DbPools::read_after_lsn) only the ones that have
+/// caught up. [`Candidate::index`] identifies the replica either way. It
+/// returns the position in `candidates` of the replica to read from, or
+/// `None` to read from the primary. A position past the end of `candidates`
+/// also means the primary.
+///
+/// The built-in [`LoadBalance`](crate::LoadBalance) strategies are
+/// implementations of this trait ([`RoundRobin`](crate::RoundRobin),
+/// [`Random`](crate::Random), [`LeastBusy`](crate::LeastBusy) and
+/// [`LeastLatency`](crate::LeastLatency)). They only pick replicas with a
+/// non-zero [`weight`](Candidate::weight), so unhealthy, lagging or
+/// circuit-broken replicas are skipped; custom policies should usually do
+/// the same. `select` runs on every read, so keep it cheap and don't block.
+///
+/// Set a policy with [`DbPools::routing_policy`]. Clones of the `DbPools`
+/// share it, so state such as a round-robin cursor is shared too.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx::PgPool;
+/// use sqlx_pool_router::{Candidate, DbPools, RoutingPolicy};
+///
+/// /// Send reads to the first available replica, and only use the
+/// /// others when it's out of rotation.
+/// #[derive(Debug)]
+/// struct Preferred;
+///
+/// impl RoutingPolicy for Preferred {
+///     fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+///         candidates.iter().position(|candidate| candidate.weight() > 0)
+///     }
+/// }
+///
+/// # fn example(primary: PgPool, replicas: Vec<PgPool>) {
+/// let pools = DbPools::with_replicas(primary, replicas).routing_policy(Preferred);
+/// # }
+/// ```
+pub trait RoutingPolicy: fmt::Debug + Send + Sync + 'static {
+    /// Pick the replica to read from, as a position in `candidates`, or
+    /// `None` for the primary.
+    fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize>;
+}
+
+/// Candidates routed without allocating; most deployments have fewer replicas.
+const INLINE_CANDIDATES: usize = 16;
+
+/// A replica offered to a [`RoutingPolicy`], with its current routing stats.
+///
+/// Stats are read when the methods are called, so a policy only pays for
+/// the ones it uses.
+#[derive(Clone, Copy)]
+pub struct Candidate<'a> {
+    index: usize,
+    node: &'a ReplicaNode,
+}
+
+impl<'a> Candidate<'a> {
+    pub(crate) fn node(&self) -> &'a ReplicaNode {
+        self.node
+    }
+
+    /// Position of the replica, in the order the replicas were provided.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// The replica's pool.
+    pub fn pool(&self) -> &'a PgPool {
+        &self.node.pool
+    }
+
+    /// The availability zone the replica is tagged with, see
+    /// [`Replica::zone`](crate::Replica::zone).
+    pub fn zone(&self) -> Option<&'a str> {
+        self.node.zone.as_deref()
+    }
+
+    /// The weight to route with: the configured weight, or `0` when the
+    /// replica is unhealthy, lagging beyond the limit or has an open circuit.
+    pub fn weight(&self) -> u32 {
+        self.node.effective_weight()
+    }
+
+    /// The configured weight, see [`Replica::weight`](crate::Replica::weight).
+    pub fn configured_weight(&self) -> u32 {
+        self.node.weight()
+    }
+
+    /// Whether the health checker considers the replica healthy.
+    pub fn is_healthy(&self) -> bool {
+        self.node.health.is_healthy()
+    }
+
+    /// Whether the replica is within the replication lag limits.
+    pub fn is_within_lag_limit(&self) -> bool {
+        self.node.lag.is_within_limit()
+    }
+
+    /// Replication lag from the last sample, if lag is being monitored.
+    pub fn lag(&self) -> Option<Duration> {
+        self.node.lag.lag()
+    }
+
+    /// State of the replica's circuit breaker.
+    pub fn circuit(&self) -> CircuitState {
+        self.node.breaker.state()
+    }
+
+    /// Connections currently checked out of the replica's pool.
+    pub fn in_use(&self) -> usize {
+        in_use(&self.node.pool)
+    }
+
+    /// Idle connections in the replica's pool.
+    pub fn num_idle(&self) -> usize {
+        self.node.pool.num_idle()
+    }
+
+    /// Average read latency, if any read on the replica has been measured.
+    /// See [`LoadBalance::LeastLatency`](crate::LoadBalance::LeastLatency).
+    pub fn latency(&self) -> Option<Duration> {
+        self.node.latency.get()
+    }
+}
+
+impl fmt::Debug for Candidate<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Candidate")
+            .field("index", &self.index)
+            .field("zone", &self.zone())
+            .field("weight", &self.weight())
+            .field("in_use", &self.in_use())
+            .field("latency", &self.latency())
+            .finish_non_exhaustive()
+    }
+}
+
+impl DbPools {
+    /// Set the policy that picks a replica for each read.
+    ///
+    /// Replaces the [`load_balance`](Self::load_balance) strategy. See
+    /// [`RoutingPolicy`] for an example.
+    pub fn routing_policy(mut self, policy: impl RoutingPolicy) -> Self {
+        self.routing = std::sync::Arc::new(policy);
+        self
+    }
+
+    /// The replica the routing policy picks, or `None` for the primary.
+    pub(crate) fn select_replica(&self) -> Option<&ReplicaNode> {
+        self.select_among(0..self.replicas.len())
+    }
+
+    /// The replica the routing policy picks from the ones at `indices`, or
+    /// `None` for the primary.
+    ///
+    /// Candidates are collected on the stack, so routing doesn't allocate
+    /// unless there are more than [`INLINE_CANDIDATES`] of them.
+    pub(crate) fn select_among(
+        &self,
+        indices: impl IntoIterator<Item = usize>,
+    ) -> Option<&ReplicaNode> {
+        let placeholder = Candidate {
+            index: 0,
+            node: self.replicas.first()?,
+        };
+        let mut inline = [placeholder; INLINE_CANDIDATES];
+        let mut spilled = Vec::new();
+        let mut len = 0;
+        for index in indices {
+            let candidate = Candidate {
+                index,
+                node: &self.replicas[index],
+            };
+            if len < INLINE_CANDIDATES {
+                inline[len] = candidate;
+            } else {
+                if len == INLINE_CANDIDATES {
+                    spilled.extend_from_slice(&inline);
+                }
+                spilled.push(candidate);
+            }
+            len += 1;
+        }
+        let candidates = if len <= INLINE_CANDIDATES {
+            &inline[..len]
+        } else {
+            &spilled[..]
+        };
+        let position = self.routing.select(candidates)?;
+        candidates.get(position).map(Candidate::node)
+    }
+}
@@ -0,0 +1,94 @@
+//! Sessions that read from the primary for a while after writing.
+
+use crate::{DbPools, PoolProvider, ReadPool, WritePool};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// A logical session over [`DbPools`] that keeps reads on the primary for a
+/// window after each write.
+///
+/// Calling [`write()`](PoolProvider::write) records the time of the write.
+/// Until `window` has passed, [`read()`](PoolProvider::read) returns the
+/// primary so the session sees its own writes; after that, reads are routed
+/// normally. This is a lighter-weight alternative to tracking WAL positions
+/// with [`DbPools::read_after_lsn`].
+///
+/// Clones share the same last-write time, so a session can be cloned into
+/// every task that works on behalf of the same user or request.
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use std::time::Duration;
+/// use sqlx_pool_router::{DbPools, PoolProvider};
+///
+/// # async fn example(pools: DbPools) -> Result<(), sqlx::Error> {
+/// let session = pools.session(Duration::from_secs(2));
+///
+/// sqlx::query("UPDATE users SET name = 'Alice' WHERE id = 1")
+///     .execute(session.write())
+///     .await?;
+///
+/// // Within 2 seconds of the write, this reads from the primary
+/// let name: String = sqlx::query_scalar("SELECT name FROM users WHERE id = 1")
+///     .fetch_one(session.read())
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug)]
+pub struct Session {
+    pools: DbPools,
+    window: Duration,
+    last_write: Arc<Mutex<Option<Instant>>>,
+}
+
+impl Session {
+    /// Record a write made without going through [`write()`](PoolProvider::write).
+    ///
+    /// Starts a new sticky window, as if `write()` had just been called.
+    pub fn mark_write(&self) {
+        *self.last_write.lock().unwrap() = Some(Instant::now());
+    }
+
+    /// When this session last wrote, if it has written at all.
+    pub fn last_write(&self) -> Option<Instant> {
+        *self.last_write.lock().unwrap()
+    }
+
+    /// Whether reads currently go to the primary because of a recent write.
+    pub fn is_sticky(&self) -> bool {
+        self.last_write()
+            .is_some_and(|at| at.elapsed() < self.window)
+    }
+
+    /// The pools this session routes through.
+    pub fn pools(&self) -> &DbPools {
+        &self.pools
+    }
+}
+
+impl PoolProvider for Session {
+    fn read(&self) -> &ReadPool {
+        if self.is_sticky() {
+            return self.pools.write();
+        }
+        self.pools.read()
+    }
+
+    fn write(&self) -> &WritePool {
+        self.mark_write();
+        self.pools.write()
+    }
+}
+
+impl DbPools {
+    /// Start a [`Session`] whose reads stay on the primary for `window` after each write.
+    pub fn session(&self, window: Duration) -> Session {
+        Session {
+            pools: self.clone(),
+            window,
+            last_write: Arc::new(Mutex::new(None)),
+        }
+    }
+}
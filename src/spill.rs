@@ -0,0 +1,98 @@
+//! Overflowing reads to the primary when a replica pool is saturated.
+
+use crate::DbPools;
+use sqlx::PgPool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+
+/// Settings for [`DbPools::spill_to_primary`].
+///
+/// A replica pool is saturated when it has opened every connection it's
+/// allowed (`max_connections`) and none of them is idle, so a new read
+/// would queue for a connection. Such reads are sent to the primary instead,
+/// but only while the primary has more than [`headroom`](Self::headroom)
+/// idle connections, which stay reserved for writes.
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::SpilloverConfig;
+///
+/// // Keep at least 4 idle primary connections for writes
+/// let config = SpilloverConfig::new().headroom(4);
+/// ```
+#[derive(Clone, Debug)]
+pub struct SpilloverConfig {
+    headroom: usize,
+}
+
+impl SpilloverConfig {
+    /// Create a config with the default headroom of 2 idle connections.
+    pub fn new() -> Self {
+        Self { headroom: 2 }
+    }
+
+    /// Idle primary connections that spilled reads may not use.
+    pub fn headroom(mut self, connections: usize) -> Self {
+        self.headroom = connections;
+        self
+    }
+}
+
+impl Default for SpilloverConfig {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether every connection the pool may open is open and in use.
+fn is_saturated(pool: &PgPool) -> bool {
+    pool.size() >= pool.options().get_max_connections() && pool.num_idle() == 0
+}
+
+impl DbPools {
+    /// Send reads to the primary while the selected replica pool is saturated.
+    ///
+    /// Avoids acquire timeouts during read bursts without sizing the
+    /// replica pools for the peak. See [`SpilloverConfig`] for when a read
+    /// spills. Off by default.
+    ///
+    /// sqlx doesn't expose the number of tasks waiting for a connection,
+    /// so a pool with every connection checked out counts as saturated even
+    /// if nothing is queuing yet.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::{DbPools, SpilloverConfig};
+    ///
+    /// # fn example(primary: PgPool, replicas: Vec<PgPool>) {
+    /// let pools = DbPools::with_replicas(primary, replicas)
+    ///     .spill_to_primary(SpilloverConfig::new().headroom(4));
+    /// # }
+    /// ```
+    pub fn spill_to_primary(mut self, config: SpilloverConfig) -> Self {
+        self.spillover = Some(Arc::new(config));
+        self
+    }
+
+    /// Whether a read selected for `replica` should go to the primary
+    /// instead, counting it if so.
+    pub(crate) fn spills(&self, replica: &PgPool) -> bool {
+        let Some(config) = &self.spillover else {
+            return false;
+        };
+        if !is_saturated(replica) || self.primary.current().num_idle() <= config.headroom {
+            return false;
+        }
+        self.read_stats.spills.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// Number of reads sent to the primary because their replica was
+    /// saturated, since the pools were created, shared between clones.
+    pub fn read_spills(&self) -> u64 {
+        self.read_stats.spills.load(Ordering::Relaxed)
+    }
+}
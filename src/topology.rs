@@ -0,0 +1,209 @@
+//! Startup validation of the primary/replica topology.
+
+use crate::DbPools;
+use sqlx::PgPool;
+use std::fmt;
+
+/// Recovery state, cluster identity and database name of a server.
+const IDENTITY_QUERY: &str = "\
+    SELECT pg_is_in_recovery(), \
+        (SELECT system_identifier FROM pg_control_system()), \
+        current_database()";
+
+/// Which pool a [`TopologyError`] refers to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PoolRole {
+    /// The primary pool.
+    Primary,
+    /// The replica at this position, in the order the replicas were provided.
+    Replica(usize),
+}
+
+impl fmt::Display for PoolRole {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PoolRole::Primary => write!(f, "primary"),
+            PoolRole::Replica(index) => write!(f, "replica {}", index),
+        }
+    }
+}
+
+/// A misconfiguration found by [`DbPools::validate`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum TopologyError {
+    /// The primary pool points at a server in recovery (a standby).
+    PrimaryInRecovery,
+    /// A replica pool points at a server that is not in recovery, such as
+    /// the primary. Opt out with [`Replica::skip_recovery_check`](crate::Replica::skip_recovery_check).
+    ReplicaNotInRecovery {
+        /// Position of the replica.
+        replica: usize,
+    },
+    /// A replica belongs to a different cluster than the primary.
+    SystemIdentifierMismatch {
+        /// Position of the replica.
+        replica: usize,
+        /// The primary's `system_identifier`.
+        primary: i64,
+        /// The replica's `system_identifier`.
+        found: i64,
+    },
+    /// A replica is connected to a different database than the primary.
+    DatabaseMismatch {
+        /// Position of the replica.
+        replica: usize,
+        /// The primary's database name.
+        primary: String,
+        /// The replica's database name.
+        found: String,
+    },
+    /// A pool could not be queried.
+    Query {
+        /// The pool that failed.
+        pool: PoolRole,
+        /// The underlying error.
+        source: sqlx::Error,
+    },
+}
+
+impl fmt::Display for TopologyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TopologyError::PrimaryInRecovery => {
+                write!(f, "primary pool points at a server in recovery")
+            }
+            TopologyError::ReplicaNotInRecovery { replica } => {
+                write!(
+                    f,
+                    "replica {} points at a server that is not in recovery",
+                    replica
+                )
+            }
+            TopologyError::SystemIdentifierMismatch {
+                replica,
+                primary,
+                found,
+            } => write!(
+                f,
+                "replica {} has system identifier {}, but the primary has {}",
+                replica, found, primary
+            ),
+            TopologyError::DatabaseMismatch {
+                replica,
+                primary,
+                found,
+            } => write!(
+                f,
+                "replica {} is connected to database {:?}, but the primary is connected to {:?}",
+                replica, found, primary
+            ),
+            TopologyError::Query { pool, source } => {
+                write!(f, "failed to query {}: {}", pool, source)
+            }
+        }
+    }
+}
+
+impl std::error::Error for TopologyError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TopologyError::Query { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+pub(crate) struct Identity {
+    pub(crate) in_recovery: bool,
+    pub(crate) system_identifier: i64,
+    pub(crate) database: String,
+}
+
+async fn identity(pool: &PgPool, role: PoolRole) -> Result<Identity, TopologyError> {
+    let (in_recovery, system_identifier, database) = sqlx::query_as(IDENTITY_QUERY)
+        .fetch_one(pool)
+        .await
+        .map_err(|source| TopologyError::Query { pool: role, source })?;
+    Ok(Identity {
+        in_recovery,
+        system_identifier,
+        database,
+    })
+}
+
+/// Check a replica's identity against the primary's.
+///
+/// Replicas that skip the recovery check can be writable servers with their
+/// own cluster, such as logical replication subscribers, so only their
+/// database name is compared.
+pub(crate) fn check_replica(
+    index: usize,
+    expect_recovery: bool,
+    primary: &Identity,
+    found: Identity,
+) -> Result<(), TopologyError> {
+    if expect_recovery && !found.in_recovery {
+        return Err(TopologyError::ReplicaNotInRecovery { replica: index });
+    }
+    if expect_recovery && found.system_identifier != primary.system_identifier {
+        return Err(TopologyError::SystemIdentifierMismatch {
+            replica: index,
+            primary: primary.system_identifier,
+            found: found.system_identifier,
+        });
+    }
+    if found.database != primary.database {
+        return Err(TopologyError::DatabaseMismatch {
+            replica: index,
+            primary: primary.database.clone(),
+            found: found.database,
+        });
+    }
+    Ok(())
+}
+
+impl DbPools {
+    /// Check that the pools are wired up the way they're meant to be.
+    ///
+    /// Intended to run once at startup. Verifies that:
+    ///
+    /// - the primary is not in recovery (`pg_is_in_recovery()`),
+    /// - every replica is in recovery, unless built with
+    ///   [`Replica::skip_recovery_check`](crate::Replica::skip_recovery_check),
+    /// - every replica reports the same `system_identifier` (from
+    ///   `pg_control_system()`) and database name as the primary. Replicas
+    ///   that skip the recovery check only need the same database name, as
+    ///   a logical replication subscriber is a separate cluster.
+    ///
+    /// This catches swapped primary/replica URLs, replicas that point at
+    /// the primary, and replicas of the wrong cluster or database.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    /// use sqlx::PgPool;
+    /// use sqlx_pool_router::{DbPools, TopologyError};
+    ///
+    /// # async fn example(primary: PgPool, replica: PgPool) {
+    /// let pools = DbPools::with_replica(primary, replica);
+    /// match pools.validate().await {
+    ///     Ok(()) => {}
+    ///     Err(TopologyError::PrimaryInRecovery) => panic!("primary and replica URLs are swapped"),
+    ///     Err(e) => panic!("invalid database topology: {}", e),
+    /// }
+    /// # }
+    /// ```
+    pub async fn validate(&self) -> Result<(), TopologyError> {
+        let primary = identity(self.primary.current(), PoolRole::Primary).await?;
+        if primary.in_recovery {
+            return Err(TopologyError::PrimaryInRecovery);
+        }
+
+        for (index, replica) in self.replicas.iter().enumerate() {
+            let found = identity(&replica.pool, PoolRole::Replica(index)).await?;
+            check_replica(index, replica.expect_recovery, &primary, found)?;
+        }
+        Ok(())
+    }
+}
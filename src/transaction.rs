@@ -0,0 +1,119 @@
+//! Transactions opened on the pool that matches their role.
+
+use crate::PoolProvider;
+use sqlx::{Postgres, Transaction};
+use std::fmt;
+
+/// A transaction isolation level.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// `READ COMMITTED`, PostgreSQL's default.
+    ReadCommitted,
+    /// `REPEATABLE READ`: every statement sees the same snapshot.
+    RepeatableRead,
+    /// `SERIALIZABLE`. Not available on hot standbys.
+    Serializable,
+}
+
+impl fmt::Display for IsolationLevel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IsolationLevel::ReadCommitted => write!(f, "READ COMMITTED"),
+            IsolationLevel::RepeatableRead => write!(f, "REPEATABLE READ"),
+            IsolationLevel::Serializable => write!(f, "SERIALIZABLE"),
+        }
+    }
+}
+
+/// Modes for transactions opened with
+/// [`begin_read_with`](PoolProvider::begin_read_with) and
+/// [`begin_write_with`](PoolProvider::begin_write_with).
+///
+/// # Example
+///
+/// ```rust
+/// use sqlx_pool_router::{IsolationLevel, TransactionOptions};
+///
+/// // A consistent snapshot for a long report that never fails with a
+/// // serialization error
+/// let options = TransactionOptions::new()
+///     .isolation(IsolationLevel::Serializable)
+///     .deferrable();
+/// ```
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TransactionOptions {
+    isolation: Option<IsolationLevel>,
+    deferrable: bool,
+}
+
+impl TransactionOptions {
+    /// Use the server's default isolation level, not deferrable.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the isolation level.
+    pub fn isolation(mut self, isolation: IsolationLevel) -> Self {
+        self.isolation = Some(isolation);
+        self
+    }
+
+    /// Make the transaction `DEFERRABLE`.
+    ///
+    /// Only has an effect on `SERIALIZABLE READ ONLY` transactions, which then
+    /// wait for a snapshot that can't cause serialization failures.
+    pub fn deferrable(mut self) -> Self {
+        self.deferrable = true;
+        self
+    }
+
+    /// The `SET TRANSACTION` statement for these options, if one is needed.
+    fn statement(&self, read_only: bool) -> Option<String> {
+        let mut modes = Vec::new();
+        if read_only {
+            modes.push("READ ONLY".to_string());
+        }
+        if let Some(isolation) = self.isolation {
+            modes.push(format!("ISOLATION LEVEL {}", isolation));
+        }
+        if self.deferrable {
+            modes.push("DEFERRABLE".to_string());
+        }
+        if modes.is_empty() {
+            None
+        } else {
+            Some(format!("SET TRANSACTION {}", modes.join(", ")))
+        }
+    }
+}
+
+pub(crate) async fn begin_read<P: PoolProvider>(
+    pools: &P,
+    options: TransactionOptions,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    // Hot standbys reject serializable transactions
+    let pool = if options.isolation == Some(IsolationLevel::Serializable) {
+        pools.write().pool()
+    } else {
+        pools.read().pool()
+    };
+    begin(pool, options.statement(true)).await
+}
+
+pub(crate) async fn begin_write<P: PoolProvider>(
+    pools: &P,
+    options: TransactionOptions,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    begin(pools.write().pool(), options.statement(false)).await
+}
+
+async fn begin(
+    pool: &sqlx::PgPool,
+    statement: Option<String>,
+) -> Result<Transaction<'static, Postgres>, sqlx::Error> {
+    let mut tx = pool.begin().await?;
+    if let Some(statement) = statement {
+        sqlx::query(&statement).execute(&mut *tx).await?;
+    }
+    Ok(tx)
+}
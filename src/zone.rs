@@ -0,0 +1,90 @@
+//! Availability-zone-aware replica preference.
+
+use crate::balance::{pick_by_ticket, total_weight};
+use crate::routing::{Candidate, RoutingPolicy};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// A [`RoutingPolicy`] that keeps reads in the local availability zone.
+///
+/// Replicas are tagged with [`Replica::zone`](crate::Replica::zone). Reads
+/// go to in-rotation replicas in the local zone; when none is available
+/// (unhealthy, lagging, circuit open or drained), to replicas in other
+/// zones, including untagged ones; and only then to the primary. Within
+/// each tier replicas are visited in weighted round-robin order.
+///
+/// Without a local zone, every replica is in the same tier and this
+/// behaves like [`RoundRobin`](crate::RoundRobin).
+///
+/// # Example
+///
+/// ```rust,no_run
+/// use sqlx::PgPool;
+/// use sqlx_pool_router::{DbPools, Replica, ZoneAffinity};
+///
+/// # fn example(primary: PgPool, replica_a: PgPool, replica_b: PgPool) {
+/// // DB_LOCAL_ZONE=us-east-1a
+/// let pools = DbPools::with_replicas(
+///     primary,
+///     vec![
+///         Replica::new(replica_a).zone("us-east-1a"),
+///         Replica::new(replica_b).zone("us-east-1b"),
+///     ],
+/// )
+/// .routing_policy(ZoneAffinity::from_env());
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct ZoneAffinity {
+    local: Option<String>,
+    cursor: AtomicUsize,
+}
+
+impl ZoneAffinity {
+    /// Environment variable read by [`from_env`](Self::from_env).
+    pub const ENV_VAR: &'static str = "DB_LOCAL_ZONE";
+
+    /// Prefer replicas in `zone`.
+    pub fn new(zone: impl Into<String>) -> Self {
+        Self {
+            local: Some(zone.into()),
+            cursor: AtomicUsize::new(0),
+        }
+    }
+
+    /// Take the local zone from [`ENV_VAR`](Self::ENV_VAR).
+    ///
+    /// If the variable isn't set or is empty, no zone is preferred.
+    pub fn from_env() -> Self {
+        Self::from_env_var(Self::ENV_VAR)
+    }
+
+    /// Take the local zone from the environment variable `name`, such as
+    /// one your orchestrator sets.
+    ///
+    /// If the variable isn't set or is empty, no zone is preferred.
+    pub fn from_env_var(name: &str) -> Self {
+        match std::env::var(name) {
+            Ok(zone) if !zone.is_empty() => Self::new(zone),
+            _ => Self::default(),
+        }
+    }
+
+    /// The zone reads are kept in, if any.
+    pub fn local_zone(&self) -> Option<&str> {
+        self.local.as_deref()
+    }
+}
+
+impl RoutingPolicy for ZoneAffinity {
+    fn select(&self, candidates: &[Candidate<'_>]) -> Option<usize> {
+        let ticket = self.cursor.fetch_add(1, Ordering::Relaxed) as u64;
+        let pick = |include: &dyn Fn(usize) -> bool| {
+            let total = total_weight(candidates, include)?;
+            pick_by_ticket(candidates, include, ticket % total)
+        };
+        let local = |index: usize| {
+            self.local.is_some() && candidates[index].zone() == self.local.as_deref()
+        };
+        pick(&local).or_else(|| pick(&|_| true))
+    }
+}